@@ -0,0 +1,274 @@
+//
+// Copyright 2021-Present (c) Raja Lehtihet & Wael El Oraiby
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice,
+// this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice,
+// this list of conditions and the following disclaimer in the documentation
+// and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors
+// may be used to endorse or promote products derived from this software without
+// specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE
+// LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR
+// CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF
+// SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS
+// INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN
+// CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE)
+// ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE
+// POSSIBILITY OF SUCH DAMAGE.
+//
+use crate::list::*;
+
+type L<E> = List<E>;
+
+//
+// Two lists deque: the front list holds the oldest elements (top being the front of the deque),
+// the back list holds the newest ones (top being the back of the deque). Whenever one of them
+// empties while the other holds more than one element, the other one is split in halves. Both
+// ends are then amortized O(1).
+//
+
+// split `l` so that its bottom half becomes the (reversed) other side
+fn split<E: Clone>(l: &L<E>) -> (L<E>, L<E>) {
+    let mut v = l.to_vec();
+    let other = v.split_off(v.len() / 2);
+    (
+        L::from_vec(v),
+        L::from_vec(other.into_iter().rev().collect()),
+    )
+}
+
+fn make<E: Clone>(front: L<E>, back: L<E>) -> Deque<E> {
+    match (front.len(), back.len()) {
+        (0, b) if b > 1 => {
+            let (back, front) = split(&back);
+            Deque { front, back }
+        }
+        (f, 0) if f > 1 => {
+            let (front, back) = split(&front);
+            Deque { front, back }
+        }
+        _ => Deque { front, back },
+    }
+}
+
+#[derive(Clone)]
+pub struct Deque<E: Clone> {
+    front: L<E>,
+    back: L<E>,
+}
+
+impl<E: Clone> Deque<E> {
+    ///
+    /// create and return a new empty deque
+    ///
+    pub fn empty() -> Self {
+        Self {
+            front: L::empty(),
+            back: L::empty(),
+        }
+    }
+
+    ///
+    /// create and return a new deque with the new element added at the front
+    ///
+    pub fn push_front(&self, e: E) -> Self {
+        make(self.front.push(e), self.back.clone())
+    }
+
+    ///
+    /// create and return a new deque with the new element added at the back
+    ///
+    pub fn push_back(&self, e: E) -> Self {
+        make(self.front.clone(), self.back.push(e))
+    }
+
+    ///
+    /// create a new deque with the front element removed and returned (panics if the deque is empty)
+    ///
+    pub fn pop_front(&self) -> (E, Self) {
+        match self.front.len() {
+            0 => (self.back.top().clone(), Self::empty()),
+            _ => (
+                self.front.top().clone(),
+                make(self.front.pop(), self.back.clone()),
+            ),
+        }
+    }
+
+    ///
+    /// create a new deque with the back element removed and returned (panics if the deque is empty)
+    ///
+    pub fn pop_back(&self) -> (E, Self) {
+        match self.back.len() {
+            0 => (self.front.top().clone(), Self::empty()),
+            _ => (
+                self.back.top().clone(),
+                make(self.front.clone(), self.back.pop()),
+            ),
+        }
+    }
+
+    ///
+    /// return a reference to the front element, or None if the deque is empty
+    ///
+    pub fn front(&self) -> Option<&E> {
+        match (self.front.len(), self.back.len()) {
+            (0, 0) => None,
+            (0, _) => Some(self.back.top()),
+            _ => Some(self.front.top()),
+        }
+    }
+
+    ///
+    /// return a reference to the back element, or None if the deque is empty
+    ///
+    pub fn back(&self) -> Option<&E> {
+        match (self.front.len(), self.back.len()) {
+            (0, 0) => None,
+            (_, 0) => Some(self.front.top()),
+            _ => Some(self.back.top()),
+        }
+    }
+
+    ///
+    /// return true if the deque is empty
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///
+    /// return the length of the current deque
+    ///
+    pub fn len(&self) -> usize {
+        self.front.len() + self.back.len()
+    }
+
+    ///
+    /// walk the deque and build a vector and return it (front element first)
+    ///
+    pub fn to_vec(&self) -> Vec<E> {
+        let mut v = self.front.to_vec();
+        v.extend(self.back.rev().iter());
+        v
+    }
+
+    ///
+    /// returns an iterator (front element first)
+    ///
+    pub fn iter(&self) -> DequeIter<E> {
+        DequeIter {
+            deque: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DequeIter<E: Clone> {
+    deque: Deque<E>,
+}
+
+impl<E: Clone> std::iter::Iterator for DequeIter<E> {
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.deque.is_empty() {
+            true => None,
+            false => {
+                let (e, d) = self.deque.pop_front();
+                self.deque = d;
+                Some(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deque::*;
+    use std::collections::VecDeque;
+
+    static mut SEED: i64 = 777;
+
+    fn rand() -> i32 {
+        unsafe {
+            SEED = SEED.wrapping_mul(1664525).wrapping_add(1013904223);
+            (SEED >> 24) as i32
+        }
+    }
+
+    #[test]
+    fn push_pop() {
+        let d = Deque::empty().push_back(2).push_back(3).push_front(1);
+        assert_eq!(d.len(), 3);
+        assert_eq!(d.front(), Some(&1));
+        assert_eq!(d.back(), Some(&3));
+        assert_eq!(d.to_vec(), vec![1, 2, 3]);
+
+        let (e, d) = d.pop_back();
+        assert_eq!(e, 3);
+        let (e, d) = d.pop_back();
+        assert_eq!(e, 2);
+        assert_eq!(d.front(), Some(&1));
+        assert_eq!(d.back(), Some(&1));
+        let (e, d) = d.pop_front();
+        assert_eq!(e, 1);
+        assert!(d.is_empty());
+        assert_eq!(d.front(), None);
+        assert_eq!(d.back(), None);
+    }
+
+    #[test]
+    fn against_vecdeque() {
+        let mut expected = VecDeque::new();
+        let mut d = Deque::empty();
+        for i in 0..100000 {
+            match rand() & 3 {
+                0 => {
+                    d = d.push_front(i);
+                    expected.push_front(i);
+                }
+                1 => {
+                    d = d.push_back(i);
+                    expected.push_back(i);
+                }
+                2 if !d.is_empty() => {
+                    let (e, n) = d.pop_front();
+                    assert_eq!(Some(e), expected.pop_front());
+                    d = n;
+                }
+                3 if !d.is_empty() => {
+                    let (e, n) = d.pop_back();
+                    assert_eq!(Some(e), expected.pop_back());
+                    d = n;
+                }
+                _ => (),
+            }
+            assert_eq!(d.len(), expected.len());
+            assert_eq!(d.front(), expected.front());
+            assert_eq!(d.back(), expected.back());
+        }
+
+        assert_eq!(d.to_vec(), expected.iter().cloned().collect::<Vec<_>>());
+        assert_eq!(d.iter().collect::<Vec<_>>(), d.to_vec());
+
+        // drain from the back only
+        while !d.is_empty() {
+            let (e, n) = d.pop_back();
+            assert_eq!(Some(e), expected.pop_back());
+            d = n;
+        }
+        assert!(expected.is_empty());
+    }
+}
@@ -28,9 +28,10 @@ use std::marker::PhantomData;
 // ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE
 // POSSIBILITY OF SUCH DAMAGE.
 //
-use crate::{Hashable, TRIE_BITS, TRIE_MASK, TRIE_SIZE};
+use crate::hashset::HashSetNode;
+use crate::Arc;
+use crate::{HashSet, Hashable, Map, TRIE_BITS, TRIE_MASK, TRIE_SIZE};
 use std::mem::*;
-use std::sync::Arc;
 
 #[derive(Clone)]
 enum HashMapNode<K: Hashable + Eq + Clone, V: Clone> {
@@ -117,13 +118,13 @@ impl<K: Hashable + Eq + Clone, V: Clone> HashMapNode<K, V> {
         }
     }
 
-    fn find(&self, l: u32, k: &K) -> Option<&V> {
+    fn find(&self, l: u32, k: &K) -> Option<(&K, &V)> {
         let kh = k.hash() as usize;
         let idx = kh.wrapping_shr(l) & TRIE_MASK;
 
         match self {
             Empty => None,
-            One(hh, k2, v) if kh == *hh && k == k2 => Some(v),
+            One(hh, k2, v) if kh == *hh && k == k2 => Some((k2, v)),
             One(_, _, _) => None,
             Node(_, slice) => slice[idx].find(l + TRIE_BITS, k),
         }
@@ -156,6 +157,74 @@ impl<K: Hashable + Eq + Clone, V: Clone> HashMapNode<K, V> {
         }
     }
 
+    // returns true as soon as an entry satisfies `f`
+    fn any<F: Fn(&K, &V) -> bool>(&self, f: &F) -> bool {
+        match self {
+            Empty => false,
+            One(_, k, v) => f(k, v),
+            Node(_, slice) => slice.iter().any(|n| n.any(f)),
+        }
+    }
+
+    fn count<F: Fn(&K, &V) -> bool>(&self, f: &F) -> usize {
+        match self {
+            Empty => 0,
+            One(_, k, v) => f(k, v) as usize,
+            Node(_, slice) => slice.iter().map(|n| n.count(f)).sum(),
+        }
+    }
+
+    // keep the entries whose key satisfies `f`, collapsing the emptied nodes. Nodes that lose no
+    // entry are shared. Returns the new node and its entry count
+    fn filter<F: Fn(&K) -> bool>(h: &N<K, V>, f: &F) -> (N<K, V>, usize) {
+        match h {
+            Empty => (Empty, 0),
+            One(_, k, _) if f(k) => (h.clone(), 1),
+            One(_, _, _) => (Empty, 0),
+            Node(size, slice) => {
+                let mut slice2 = N::new_empty_slice();
+                let mut count = 0;
+                for (i, n) in slice.iter().enumerate() {
+                    let (n, c) = N::filter(n, f);
+                    slice2[i] = n;
+                    count += c;
+                }
+                match count {
+                    0 => (Empty, 0),
+                    1 => (
+                        slice2.iter().find(|n| !matches!(n, Empty)).unwrap().clone(),
+                        1,
+                    ),
+                    _ if count == *size => (h.clone(), count),
+                    _ => (Node(count, Arc::new(slice2)), count),
+                }
+            }
+        }
+    }
+
+    // mirror the trie, dropping the values: the keys keep their hashes and positions
+    fn key_set(&self) -> HashSetNode<K> {
+        match self {
+            Empty => HashSetNode::Empty,
+            One(kh, k, _) => HashSetNode::One(*kh, k.clone()),
+            Node(size, slice) => {
+                let mut slice2 = HashSetNode::new_empty_slice();
+                for (n2, n) in slice2.iter_mut().zip(slice.iter()) {
+                    *n2 = n.key_set();
+                }
+                HashSetNode::Node(*size, Arc::new(slice2))
+            }
+        }
+    }
+
+    fn node_count(&self) -> usize {
+        match self {
+            Empty => 0,
+            One(_, _, _) => 1,
+            Node(_, slice) => 1 + slice.iter().map(|n| n.node_count()).sum::<usize>(),
+        }
+    }
+
     fn to_vec_internal(&self, v: &mut Vec<(K, V)>) {
         match self {
             Empty => (),
@@ -192,6 +261,36 @@ impl<K: Hashable + Eq + Clone, V: Clone> HashMap<K, V> {
         }
     }
 
+    ///
+    /// create and return a new map containing only the given key, value pair
+    ///
+    pub fn singleton(k: K, v: V) -> Self {
+        Self {
+            n: H::new(One(k.hash() as usize, k, v)),
+            count: 1,
+        }
+    }
+
+    ///
+    /// create and return a new map out of the given pairs, or `Err` with the first key found twice
+    /// (instead of overwriting its value as `insert` does)
+    ///
+    pub fn from_pairs_checked<I: IntoIterator<Item = (K, V)>>(iter: I) -> Result<Self, K> {
+        let mut m = Self::empty();
+        for (k, v) in iter {
+            match N::insert(m.n.as_ref(), 0, k.clone(), v) {
+                Some(n) => {
+                    m = Self {
+                        n: H::new(n),
+                        count: m.count + 1,
+                    }
+                }
+                None => return Err(k),
+            }
+        }
+        Ok(m)
+    }
+
     ///
     /// create and return a new map containing the new key, value pair
     ///
@@ -241,16 +340,58 @@ impl<K: Hashable + Eq + Clone, V: Clone> HashMap<K, V> {
     /// search for a key and return a pointer to the value if the key exists, None otherwise
     ///
     pub fn find(&self, k: &K) -> Option<&V> {
+        self.n.as_ref().find(0, k).map(|(_, v)| v)
+    }
+
+    ///
+    /// search for a key and return pointers to the stored key and its value if the key exists,
+    /// None otherwise
+    ///
+    pub fn get_key_value(&self, k: &K) -> Option<(&K, &V)> {
         self.n.as_ref().find(0, k)
     }
 
     ///
-    /// walk the list/stack and build a vector of keys and return it
+    /// return true if at least one entry satisfies `f`. Stops at the first match
+    ///
+    pub fn any<F: Fn(&K, &V) -> bool>(&self, f: F) -> bool {
+        self.n.any(&f)
+    }
+
+    ///
+    /// return true if every entry satisfies `f` (true for an empty map). Stops at the first mismatch
+    ///
+    pub fn all<F: Fn(&K, &V) -> bool>(&self, f: F) -> bool {
+        !self.n.any(&|k, v| !f(k, v))
+    }
+
+    ///
+    /// return the number of entries satisfying `f`
+    ///
+    pub fn count<F: Fn(&K, &V) -> bool>(&self, f: F) -> usize {
+        self.n.count(&f)
+    }
+
+    ///
+    /// build and return a vector of the key, value pairs. They come in trie order, which depends on
+    /// the hashes of the keys: use `to_sorted_vec` for a deterministic order
     ///
     pub fn to_vec(&self) -> Vec<(K, V)> {
         self.n.to_vec()
     }
 
+    ///
+    /// build and return a vector of the key, value pairs sorted by key
+    ///
+    pub fn to_sorted_vec(&self) -> Vec<(K, V)>
+    where
+        K: Ord,
+    {
+        let mut v = self.to_vec();
+        v.sort_by(|a, b| a.0.cmp(&b.0));
+        v
+    }
+
     ///
     /// return the number of elements in the set
     ///
@@ -265,6 +406,57 @@ impl<K: Hashable + Eq + Clone, V: Clone> HashMap<K, V> {
         self.count
     }
 
+    ///
+    /// return the number of trie nodes (inner nodes and entries), for debugging memory use: maps
+    /// sharing nodes count them each
+    ///
+    pub fn node_count(&self) -> usize {
+        self.n.node_count()
+    }
+
+    ///
+    /// create and return a new map holding the entries of `self` whose keys don't exist in `other`.
+    /// The trie is filtered in one pass, sharing the nodes that lose no entry
+    ///
+    pub fn difference<W: Clone>(&self, other: &HashMap<K, W>) -> Self {
+        let (n, count) = N::filter(self.n.as_ref(), &|k| !other.exist(k));
+        if count == self.count {
+            return self.clone();
+        }
+        Self {
+            n: H::new(n),
+            count,
+        }
+    }
+
+    ///
+    /// create and return the set of the keys. The trie is mirrored node by node, so no key is
+    /// rehashed
+    ///
+    pub fn key_set(&self) -> HashSet<K> {
+        HashSet::from_node(self.n.key_set(), self.count)
+    }
+
+    ///
+    /// create and return a std `HashMap` holding the same entries
+    ///
+    pub fn to_std(&self) -> std::collections::HashMap<K, V>
+    where
+        K: std::hash::Hash,
+    {
+        self.iter().collect()
+    }
+
+    ///
+    /// create and return an ordered map holding the same entries
+    ///
+    pub fn to_ordered(&self) -> Map<K, V>
+    where
+        K: Ord,
+    {
+        Map::from_sorted_vec(self.to_sorted_vec())
+    }
+
     ///
     /// returns an iterator
     ///
@@ -275,17 +467,25 @@ impl<K: Hashable + Eq + Clone, V: Clone> HashMap<K, V> {
                 node: self.n.clone(),
                 idx: 0,
             },
-            _phantom: PhantomData::default(),
+            _phantom: PhantomData,
         }
     }
 }
 
+impl<K: Hashable + Eq + Clone, V: Clone> From<std::collections::HashMap<K, V>> for HashMap<K, V> {
+    fn from(m: std::collections::HashMap<K, V>) -> Self {
+        m.into_iter()
+            .fold(HashMap::empty(), |acc, (k, v)| acc.insert(k, v))
+    }
+}
+
 #[derive(Clone)]
 struct Pointer<K: Clone + Eq + Hashable, V: Clone> {
     idx: usize,
     node: H<K, V>,
 }
 
+#[derive(Clone)]
 pub struct HMIter<'a, K: Clone + Eq + Hashable, V: Clone> {
     stack: Vec<Pointer<K, V>>,
     current: Pointer<K, V>,
@@ -376,6 +576,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
     fn insert() {
         let numbers = [3, 3, 0x13, 120, 4, 9, 27, 1, 45];
         let mut n = HashMap::empty();
@@ -391,6 +592,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::needless_range_loop)]
     fn insert_redundant_key_different_values() {
         let numbers = [3, 3, 0x13, 120, 4, 9, 27, 1, 45];
         let mut n = HashMap::empty();
@@ -412,6 +614,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
     fn remove() {
         let numbers = [3, 3, 0x13, 120, 4, 9, 27, 1, 45];
         let mut n = HashMap::empty();
@@ -432,6 +635,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
     fn insert_1000000() {
         let mut numbers = Vec::new();
         let mut n = HashMap::empty();
@@ -464,6 +668,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
     fn remove_1000000() {
         let mut numbers = Vec::new();
         let mut n = HashMap::empty();
@@ -495,6 +700,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
     fn iter_1000000() {
         let mut numbers = Vec::new();
         let mut n = HashMap::empty();
@@ -535,4 +741,293 @@ mod tests {
             assert_eq!(v, 1);
         }
     }
+
+    #[test]
+    fn difference() {
+        let mut base = HashMap::empty();
+        let mut tombstones = HashMap::empty();
+        for i in 0..10000 {
+            base = base.insert(i, i * i);
+            if i % 3 == 0 {
+                tombstones = tombstones.insert(i, ());
+            }
+        }
+        tombstones = tombstones.insert(20000, ());
+
+        let d = base.difference(&tombstones);
+        assert_eq!(d.len(), 10000 - 3334);
+        for i in 0..10000 {
+            assert_eq!(d.exist(&i), i % 3 != 0);
+            if i % 3 != 0 {
+                assert_eq!(*d.find(&i).unwrap(), i * i);
+            }
+        }
+
+        let all = base.difference(&base);
+        assert_eq!(all.len(), 0);
+        assert!(all.is_empty());
+        assert_eq!(all.iter().count(), 0);
+
+        assert_eq!(base.difference(&HashMap::<usize, ()>::empty()).len(), 10000);
+
+        // nothing removed: the trie is shared
+        let none = base.difference(&HashMap::<usize, ()>::empty().insert(20000, ()));
+        assert!(Arc::ptr_eq(&none.n, &base.n));
+
+        // only the paths to the removed keys are rebuilt
+        let one = base.difference(&HashMap::<usize, ()>::empty().insert(5, ()));
+        assert_eq!(one.len(), 9999);
+        assert!(!one.exist(&5));
+        assert_eq!(one.node_count(), base.node_count() - 1);
+    }
+
+    impl Hashable for std::rc::Rc<str> {
+        fn hash(&self) -> u64 {
+            self.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+                (h ^ b as u64).wrapping_mul(0x100000001b3)
+            })
+        }
+    }
+
+    #[test]
+    fn get_key_value() {
+        use std::rc::Rc;
+
+        let mut n = HashMap::empty();
+        let mut keys = Vec::new();
+        for i in 0..1000 {
+            let k: Rc<str> = Rc::from(format!("key{}", i));
+            keys.push(k.clone());
+            n = n.insert(k, i);
+        }
+
+        for (i, stored) in keys.iter().enumerate() {
+            let lookup: Rc<str> = Rc::from(format!("key{}", i));
+            let (k, v) = n.get_key_value(&lookup).unwrap();
+            assert_eq!(*v, i);
+            assert!(Rc::ptr_eq(k, stored));
+            assert!(!Rc::ptr_eq(k, &lookup));
+        }
+        assert!(n.get_key_value(&Rc::from("nope")).is_none());
+        assert!(HashMap::<usize, usize>::empty().get_key_value(&1).is_none());
+    }
+
+    #[test]
+    fn to_ordered() {
+        let mut n = HashMap::empty();
+        for _ in 0..10000 {
+            let r = rand() % 5000;
+            n = n.insert(r, r * 2);
+        }
+        let m = n.to_ordered();
+        assert_eq!(m.len(), n.len());
+        let mut v = n.to_vec();
+        v.sort();
+        assert_eq!(m.to_vec(), v);
+
+        let back = m.to_hashmap();
+        assert_eq!(back.len(), n.len());
+        for (k, v) in n.iter() {
+            assert_eq!(back.find(&k), Some(&v));
+        }
+        assert!(HashMap::<usize, usize>::empty().to_ordered().is_empty());
+    }
+
+    #[test]
+    fn drop_1000000_sequential() {
+        // the trie depth is bounded by the hash size, whatever the keys are
+        let mut n = HashMap::empty();
+        for i in 0..1000000 {
+            n = n.insert(i, i);
+        }
+        assert_eq!(n.len(), 1000000);
+        drop(n);
+    }
+
+    #[test]
+    fn singleton() {
+        let m = HashMap::singleton(3, 30);
+        assert_eq!(m.len(), 1);
+        assert_eq!(m.find(&3), Some(&30));
+        assert!(!m.exist(&0x13));
+        let m2 = m.insert(0x13, 31);
+        assert_eq!(m2.len(), 2);
+        assert_eq!(m2.find(&3), Some(&30));
+        assert!(m.remove(3).is_empty());
+    }
+
+    #[test]
+    fn any_all_count() {
+        use std::cell::Cell;
+
+        let mut n = HashMap::empty();
+        for i in 0..100000 {
+            n = n.insert(i, i % 7);
+        }
+
+        // short circuit
+        let calls = Cell::new(0);
+        assert!(n.any(|_, _| {
+            calls.set(calls.get() + 1);
+            true
+        }));
+        assert_eq!(calls.get(), 1);
+
+        calls.set(0);
+        assert!(!n.all(|_, _| {
+            calls.set(calls.get() + 1);
+            false
+        }));
+        assert_eq!(calls.get(), 1);
+
+        calls.set(0);
+        assert!(n.any(|k, _| {
+            calls.set(calls.get() + 1);
+            *k == 5
+        }));
+        assert!(calls.get() < 100000);
+
+        assert!(n.all(|_, v| *v < 7));
+        assert!(!n.all(|k, _| *k != 99999));
+        assert!(!n.any(|_, v| *v == 7));
+
+        assert_eq!(n.count(|_, v| *v == 0), 14286);
+        assert_eq!(
+            n.count(|k, v| k % 2 == 0 && *v == 3),
+            n.iter().filter(|(k, v)| k % 2 == 0 && *v == 3).count()
+        );
+        assert_eq!(n.count(|_, _| true), n.len());
+
+        let e = HashMap::<usize, usize>::empty();
+        assert!(!e.any(|_, _| true));
+        assert!(e.all(|_, _| false));
+        assert_eq!(e.count(|_, _| true), 0);
+    }
+
+    #[test]
+    fn std_conversions() {
+        let mut std_map = std::collections::HashMap::new();
+        for _ in 0..5000 {
+            std_map.insert(rand() % 10000, rand());
+        }
+
+        let m = HashMap::from(std_map.clone());
+        assert_eq!(m.len(), std_map.len());
+        for (k, v) in std_map.iter() {
+            assert_eq!(m.find(k), Some(v));
+        }
+
+        let back = m.to_std();
+        assert_eq!(back, std_map);
+
+        assert!(HashMap::<usize, usize>::from(std::collections::HashMap::new()).is_empty());
+        assert!(HashMap::<usize, usize>::empty().to_std().is_empty());
+    }
+
+    #[test]
+    fn key_set() {
+        let mut n = HashMap::empty();
+        for _ in 0..10000 {
+            n = n.insert(rand() % 5000, rand());
+        }
+        n = n.remove(n.to_vec()[0].0);
+
+        let s = n.key_set();
+        assert_eq!(s.len(), n.len());
+        for k in 0..5000 {
+            assert_eq!(s.exist(k), n.exist(&k));
+        }
+        assert!(n.iter().all(|(k, _)| s.exist(k)));
+
+        // the set is a regular set: it can be updated as usual
+        let k = s.to_vec()[0];
+        let s2 = s.remove(k).insert(k).insert(usize::MAX);
+        assert_eq!(s2.len(), n.len() + 1);
+        assert!(s2.exist(k) && s2.exist(usize::MAX));
+
+        assert!(HashMap::<usize, usize>::empty().key_set().is_empty());
+        assert_eq!(HashMap::singleton(3, 4).key_set().to_vec(), vec![3]);
+    }
+
+    #[test]
+    fn node_count() {
+        // collect the distinct child arrays: the unit of sharing between tries
+        type Slice = [N<usize, usize>; TRIE_SIZE];
+        fn distinct(n: &N<usize, usize>, seen: &mut std::collections::HashSet<*const Slice>) {
+            if let Node(_, slice) = n {
+                if seen.insert(Arc::as_ptr(slice)) {
+                    for c in slice.iter() {
+                        distinct(c, seen);
+                    }
+                }
+            }
+        }
+
+        let mut m = HashMap::empty();
+        for i in 0..10000 {
+            m = m.insert(i, i);
+        }
+        // one node per entry, plus the inner nodes
+        let entries = m.node_count();
+        assert!(entries > 10000);
+
+        let m2 = m.clone().insert(10000, 0);
+        assert!(m2.node_count() > entries && m2.node_count() <= entries + 2);
+        assert_eq!(m2.remove(10000).len(), m.len());
+
+        // only the arrays on the path to the new key are new, at most one per trie level
+        let mut seen = std::collections::HashSet::new();
+        distinct(&m.n, &mut seen);
+        let shared = seen.len();
+        distinct(&m2.n, &mut seen);
+        let new_slices = seen.len() - shared;
+        assert!(new_slices >= 1 && new_slices <= (usize::BITS / TRIE_BITS) as usize + 1);
+
+        assert_eq!(HashMap::singleton(1, 1).node_count(), 1);
+        assert_eq!(HashMap::<usize, usize>::empty().node_count(), 0);
+    }
+
+    #[test]
+    fn from_pairs_checked() {
+        let pairs = (0..10000).map(|i| (i, i * 2)).collect::<Vec<_>>();
+        let m = HashMap::from_pairs_checked(pairs.clone()).ok().unwrap();
+        assert_eq!(m.len(), 10000);
+        for (k, v) in pairs.iter() {
+            assert_eq!(m.find(k), Some(v));
+        }
+
+        let mut dup = pairs.clone();
+        dup.insert(5000, (1234, 0));
+        dup.push((42, 0));
+        assert_eq!(HashMap::from_pairs_checked(dup).err(), Some(1234));
+
+        let same = vec![(1, 1), (1, 1)];
+        assert_eq!(HashMap::from_pairs_checked(same).err(), Some(1));
+        let empty = HashMap::<usize, usize>::from_pairs_checked(Vec::new());
+        assert!(empty.ok().unwrap().is_empty());
+    }
+
+    #[test]
+    fn to_sorted_vec() {
+        let mut m = HashMap::empty();
+        let mut reference = std::collections::BTreeMap::new();
+        for _ in 0..10000 {
+            let (k, v) = (rand(), rand());
+            m = m.insert(k, v);
+            reference.insert(k, v);
+        }
+
+        let v = m.to_sorted_vec();
+        assert_eq!(v.len(), m.len());
+        assert!(v.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(v, reference.into_iter().collect::<Vec<_>>());
+
+        // the same entries inserted in another order give the same vector
+        let other = v
+            .iter()
+            .rev()
+            .fold(HashMap::empty(), |m, (k, v)| m.insert(*k, *v));
+        assert_eq!(other.to_sorted_vec(), v);
+        assert!(HashMap::<usize, usize>::empty().to_sorted_vec().is_empty());
+    }
 }
@@ -28,12 +28,12 @@ use std::marker::PhantomData;
 // ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE
 // POSSIBILITY OF SUCH DAMAGE.
 //
-use crate::{Hashable, TRIE_BITS, TRIE_MASK, TRIE_SIZE};
+use crate::Arc;
+use crate::{Hashable, Set, TRIE_BITS, TRIE_MASK, TRIE_SIZE};
 use std::mem::*;
-use std::sync::Arc;
 
 #[derive(Clone)]
-enum HashSetNode<K: Hashable + Eq + Clone> {
+pub(crate) enum HashSetNode<K: Hashable + Eq + Clone> {
     Empty,
     One(usize, K),
     Node(usize, Arc<[N<K>; TRIE_SIZE]>),
@@ -49,7 +49,7 @@ impl<K: Hashable + Eq + Clone> HashSetNode<K> {
         H::new(Empty)
     }
 
-    fn new_empty_slice() -> [N<K>; TRIE_SIZE] {
+    pub(crate) fn new_empty_slice() -> [N<K>; TRIE_SIZE] {
         let mut s: [MaybeUninit<N<K>>; TRIE_SIZE] = unsafe { MaybeUninit::uninit().assume_init() };
         for i in s.iter_mut().take(TRIE_SIZE) {
             *i = MaybeUninit::new(N::Empty);
@@ -144,6 +144,42 @@ impl<K: Hashable + Eq + Clone> HashSetNode<K> {
         }
     }
 
+    // returns the node holding only the keys for which `f` returns true, along with their count.
+    // Nodes left with no key become Empty, and nodes left with a single key (at any depth below)
+    // collapse to a One: a One is valid at any level since the full hash is compared
+    fn filter<F: Fn(&K) -> bool>(h: &N<K>, f: &F) -> (N<K>, usize) {
+        match h {
+            Empty => (Empty, 0),
+            One(_, k) if f(k) => (h.clone(), 1),
+            One(_, _) => (Empty, 0),
+            Node(_, slice) => {
+                let mut slice2 = N::new_empty_slice();
+                let mut count = 0;
+                for (i, n) in slice.iter().enumerate() {
+                    let (n, c) = N::filter(n, f);
+                    slice2[i] = n;
+                    count += c;
+                }
+                match count {
+                    0 => (Empty, 0),
+                    1 => (
+                        slice2.iter().find(|n| !matches!(n, Empty)).unwrap().clone(),
+                        1,
+                    ),
+                    _ => (Node(count, Arc::new(slice2)), count),
+                }
+            }
+        }
+    }
+
+    fn node_count(&self) -> usize {
+        match self {
+            Empty => 0,
+            One(_, _) => 1,
+            Node(_, slice) => 1 + slice.iter().map(|n| n.node_count()).sum::<usize>(),
+        }
+    }
+
     fn to_vec_internal(&self, v: &mut Vec<K>) {
         match self {
             Empty => (),
@@ -180,6 +216,16 @@ impl<K: Hashable + Eq + Clone> HashSet<K> {
         }
     }
 
+    ///
+    /// create and return a new set containing only the given key
+    ///
+    pub fn singleton(k: K) -> Self {
+        Self {
+            n: H::new(One(k.hash() as usize, k)),
+            count: 1,
+        }
+    }
+
     ///
     /// insert a new key and return a new set with the new element added to it
     ///
@@ -250,6 +296,45 @@ impl<K: Hashable + Eq + Clone> HashSet<K> {
         self.count
     }
 
+    ///
+    /// return the number of trie nodes (inner nodes and keys), for debugging memory use: sets
+    /// sharing nodes count them each
+    ///
+    pub fn node_count(&self) -> usize {
+        self.n.node_count()
+    }
+
+    ///
+    /// create and return a new set holding only the keys for which `f` returns true
+    ///
+    pub fn filter<F: Fn(&K) -> bool>(&self, f: F) -> Self {
+        let (n, count) = N::filter(self.n.as_ref(), &f);
+        Self {
+            n: H::new(n),
+            count,
+        }
+    }
+
+    ///
+    /// create and return a new set with `f` applied to every key. Keys mapped to the same value
+    /// are merged, so the new set can be smaller
+    ///
+    pub fn map<W: Hashable + Eq + Clone, F: FnMut(&K) -> W>(&self, mut f: F) -> HashSet<W> {
+        self.iter().fold(HashSet::empty(), |s, k| s.insert(f(&k)))
+    }
+
+    ///
+    /// create and return an ordered set holding the same keys
+    ///
+    pub fn to_ordered(&self) -> Set<K>
+    where
+        K: Ord,
+    {
+        let mut v = self.to_vec();
+        v.sort();
+        Set::from_sorted_vec(v)
+    }
+
     ///
     /// returns an iterator
     ///
@@ -260,7 +345,15 @@ impl<K: Hashable + Eq + Clone> HashSet<K> {
                 node: self.n.clone(),
                 idx: 0,
             },
-            _phantom: PhantomData::default(),
+            _phantom: PhantomData,
+        }
+    }
+
+    // wrap a trie built elsewhere in the crate (the keys must sit where `insert` would put them)
+    pub(crate) fn from_node(n: N<K>, count: usize) -> Self {
+        Self {
+            n: H::new(n),
+            count,
         }
     }
 }
@@ -271,6 +364,7 @@ struct Pointer<E: Clone + Eq + Hashable> {
     node: H<E>,
 }
 
+#[derive(Clone)]
 pub struct HSIter<'a, E: Clone + Eq + Hashable> {
     stack: Vec<Pointer<E>>,
     current: Pointer<E>,
@@ -361,6 +455,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
     fn insert() {
         let numbers = [3, 3, 0x13, 120, 4, 9, 27, 1, 45];
         let mut n = HashSet::empty();
@@ -376,6 +471,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
     fn remove() {
         let numbers = [3, 3, 0x13, 120, 4, 9, 27, 1, 45];
         let mut n = HashSet::empty();
@@ -396,6 +492,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
     fn insert_1000000() {
         let mut numbers = Vec::new();
         let mut n = HashSet::empty();
@@ -424,6 +521,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
     fn remove_1000000() {
         let mut numbers = Vec::new();
         let mut n = HashSet::empty();
@@ -455,6 +553,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
     fn iter_1000000() {
         let mut numbers = Vec::new();
         let mut n = HashSet::empty();
@@ -490,4 +589,147 @@ mod tests {
             assert_eq!(c, 1);
         }
     }
+
+    #[test]
+    fn filter() {
+        let mut n = HashSet::empty();
+        for _ in 0..100000 {
+            n = n.insert(rand() % 50000);
+        }
+        let mut sorted = n.to_vec();
+        sorted.sort();
+
+        let even = n.filter(|k| k % 2 == 0);
+        let expected = sorted.iter().filter(|k| *k % 2 == 0).collect::<Vec<_>>();
+        assert_eq!(even.len(), expected.len());
+        let mut v = even.to_vec();
+        v.sort();
+        assert_eq!(v.iter().collect::<Vec<_>>(), expected);
+        for k in sorted.iter() {
+            assert_eq!(even.exist(*k), k % 2 == 0);
+        }
+        assert_eq!(even.iter().count(), even.len());
+
+        // the filtered set is still a valid set
+        let mut e = even.clone();
+        for k in sorted.iter() {
+            e = e.insert(*k);
+        }
+        assert_eq!(e.len(), n.len());
+        for k in expected.iter() {
+            e = e.remove(**k);
+        }
+        assert_eq!(e.len(), n.len() - even.len());
+        assert!(e.iter().all(|k| k % 2 == 1));
+
+        // emptied sets collapse
+        let few = n.filter(|k| *k < 10);
+        assert_eq!(few.len(), few.to_vec().len());
+        assert!(matches!(
+            n.filter(|k| *k == sorted[0]).n.as_ref(),
+            HashSetNode::One(_, _)
+        ));
+        let none = n.filter(|_| false);
+        assert!(none.is_empty());
+        assert!(matches!(none.n.as_ref(), HashSetNode::Empty));
+        assert_eq!(none.insert(3).len(), 1);
+        assert_eq!(n.filter(|_| true).len(), n.len());
+    }
+
+    #[test]
+    fn map() {
+        let mut n = HashSet::empty();
+        for _ in 0..100000 {
+            n = n.insert(rand() % 50000);
+        }
+        let mut expected = n.iter().map(|k| k / 2).collect::<Vec<_>>();
+        expected.sort();
+        expected.dedup();
+
+        let halves = n.map(|k| k / 2);
+        assert_eq!(halves.len(), expected.len());
+        let mut v = halves.to_vec();
+        v.sort();
+        assert_eq!(v, expected);
+
+        let shifted = n.map(|k| k + 1000000);
+        assert_eq!(shifted.len(), n.len());
+        assert!(n.iter().all(|k| shifted.exist(k + 1000000)));
+        assert!(HashSet::<usize>::empty().map(|k| *k).is_empty());
+    }
+
+    #[test]
+    fn to_ordered() {
+        let mut n = HashSet::empty();
+        for _ in 0..10000 {
+            n = n.insert(rand() % 5000);
+        }
+        let s = n.to_ordered();
+        assert_eq!(s.len(), n.len());
+        let mut v = n.to_vec();
+        v.sort();
+        assert_eq!(s.to_vec(), v);
+
+        let back = s.to_hashset();
+        assert_eq!(back.len(), n.len());
+        assert!(n.iter().all(|k| back.exist(k)));
+        assert!(HashSet::<usize>::empty().to_ordered().is_empty());
+    }
+
+    #[test]
+    fn drop_1000000_sequential() {
+        // the trie depth is bounded by the hash size, whatever the keys are
+        let mut s = HashSet::empty();
+        for i in 0..1000000 {
+            s = s.insert(i);
+        }
+        assert_eq!(s.len(), 1000000);
+        drop(s);
+    }
+
+    #[test]
+    fn singleton() {
+        let s = HashSet::singleton(3);
+        assert_eq!(s.len(), 1);
+        assert!(s.exist(3));
+        assert!(!s.exist(0x13));
+        let s2 = s.insert(0x13);
+        assert_eq!(s2.len(), 2);
+        assert!(s2.exist(3) && s2.exist(0x13));
+        assert!(s.remove(3).is_empty());
+    }
+
+    #[test]
+    fn node_count() {
+        // collect the distinct child arrays: the unit of sharing between tries
+        type Slice = [N<usize>; TRIE_SIZE];
+        fn distinct(n: &N<usize>, seen: &mut std::collections::HashSet<*const Slice>) {
+            if let Node(_, slice) = n {
+                if seen.insert(Arc::as_ptr(slice)) {
+                    for c in slice.iter() {
+                        distinct(c, seen);
+                    }
+                }
+            }
+        }
+
+        let mut s = HashSet::empty();
+        for i in 0..10000 {
+            s = s.insert(i);
+        }
+        let count = s.node_count();
+        assert!(count > 10000);
+        let s2 = s.insert(10000);
+        assert!(s2.node_count() > count && s2.node_count() <= count + 2);
+
+        // only the arrays on the path to the new key are new, at most one per trie level
+        let mut seen = std::collections::HashSet::new();
+        distinct(&s.n, &mut seen);
+        let shared = seen.len();
+        distinct(&s2.n, &mut seen);
+        let new_slices = seen.len() - shared;
+        assert!(new_slices >= 1 && new_slices <= (usize::BITS / TRIE_BITS) as usize + 1);
+        assert_eq!(HashSet::singleton(1).node_count(), 1);
+        assert_eq!(HashSet::<usize>::empty().node_count(), 0);
+    }
 }
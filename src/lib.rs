@@ -28,6 +28,7 @@
 // POSSIBILITY OF SUCH DAMAGE.
 //
 
+mod deque;
 mod hashmap;
 mod hashset;
 mod list;
@@ -36,6 +37,7 @@ mod queue;
 mod set;
 mod tree;
 
+pub use deque::*;
 pub use hashmap::*;
 pub use hashset::*;
 pub use list::*;
@@ -44,10 +46,22 @@ pub use queue::*;
 pub use set::*;
 pub use tree::*;
 
+// the shared pointer used by all the structures: `Arc` by default, so they are `Send` and `Sync`.
+// With the `single_thread` feature, it's `Rc`: cheaper (non atomic) reference counting, but the
+// structures are neither `Send` nor `Sync` anymore
+#[cfg(feature = "single_thread")]
+pub(crate) use std::rc::Rc as Arc;
+#[cfg(not(feature = "single_thread"))]
+pub(crate) use std::sync::Arc;
+
 pub(crate) const TRIE_BITS: u32 = 4;
 pub(crate) const TRIE_SIZE: usize = 1 << TRIE_BITS;
 pub(crate) const TRIE_MASK: usize = TRIE_SIZE - 1;
 
+///
+/// the hash used by `HashSet` and `HashMap`: equal keys must have equal hashes. The hash only has to
+/// be consistent within a run of the program, it doesn't need to be stable across runs
+///
 pub trait Hashable {
     fn hash(&self) -> u64;
 }
@@ -57,3 +71,224 @@ impl Hashable for usize {
         *self as u64
     }
 }
+
+///
+/// a shared value hashed and compared by pointer identity: two `Identity` are equal only if they
+/// are clones of each other, whatever the values they hold. The hash is the address of the shared
+/// value, so it differs from one run of the program to another
+///
+pub struct Identity<T>(Arc<T>);
+
+impl<T> Identity<T> {
+    pub fn new(v: T) -> Self {
+        Self(Arc::new(v))
+    }
+}
+
+impl<T> Clone for Identity<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> std::ops::Deref for Identity<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Hashable for Identity<T> {
+    fn hash(&self) -> u64 {
+        Arc::as_ptr(&self.0) as usize as u64
+    }
+}
+
+impl<T> PartialEq for Identity<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T> Eq for Identity<T> {}
+
+///
+/// a value hashed and compared by value, through its `std::hash::Hash` and `Eq` implementations.
+/// The hasher is deterministic: the hash is stable across runs (for a given Rust version)
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ByValue<T>(pub T);
+
+impl<T: std::hash::Hash> Hashable for ByValue<T> {
+    fn hash(&self) -> u64 {
+        use std::hash::Hasher;
+        let mut h = std::collections::hash_map::DefaultHasher::new();
+        std::hash::Hash::hash(&self.0, &mut h);
+        h.finish()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    // counts the allocations made by the current thread, so tests can check a code path doesn't allocate
+    struct CountingAlloc;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOC: CountingAlloc = CountingAlloc;
+
+    pub(crate) fn allocations() -> usize {
+        ALLOCATIONS.with(|a| a.get())
+    }
+
+    #[cfg(not(feature = "single_thread"))]
+    #[test]
+    fn send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<List<usize>>();
+        assert_send_sync::<Queue<usize>>();
+        assert_send_sync::<Deque<usize>>();
+        assert_send_sync::<Set<usize>>();
+        assert_send_sync::<Map<usize, usize>>();
+        assert_send_sync::<HashSet<usize>>();
+        assert_send_sync::<HashMap<usize, usize>>();
+        assert_send_sync::<Path<usize>>();
+    }
+
+    #[cfg(feature = "single_thread")]
+    #[test]
+    fn single_thread() {
+        assert!(std::any::type_name::<Arc<usize>>().contains("Rc"));
+
+        let mut l = List::empty();
+        let mut q = Queue::empty();
+        let mut d = Deque::empty();
+        let mut s = Set::empty();
+        let mut m = Map::empty();
+        let mut hs = HashSet::empty();
+        let mut hm = HashMap::empty();
+        let mut t = Path::new(0);
+        for i in 0..1000usize {
+            l = l.push(i);
+            q = q.enqueue(i);
+            d = d.push_back(i);
+            s = s.insert(i);
+            m = m.insert(i, i);
+            hs = hs.insert(i);
+            hm = hm.insert(i, i);
+            t = t.add_node(i + 1).parent();
+        }
+        assert_eq!(t.count(), 1001);
+
+        for i in 0..1000usize {
+            assert_eq!(*l.top(), 999 - i);
+            l = l.pop();
+            let (e, nq) = q.dequeue();
+            assert_eq!(e, i);
+            q = nq;
+            let (e, nd) = d.pop_front();
+            assert_eq!(e, i);
+            d = nd;
+            s = s.remove(i);
+            m = m.remove(i);
+            hs = hs.remove(i);
+            hm = hm.remove(i);
+            t = t.find_child(|d| *d == i + 1).unwrap().remove_node();
+        }
+        assert!(l.is_empty() && q.is_empty() && d.is_empty());
+        assert!(s.is_empty() && m.is_empty() && hs.is_empty() && hm.is_empty());
+        assert_eq!(t.count(), 1);
+    }
+
+    #[test]
+    fn identity_by_value() {
+        let a = Identity::new(String::from("a"));
+        let a2 = Identity::new(String::from("a"));
+        let ids = HashSet::empty().insert(a.clone()).insert(a2.clone());
+        // same value, different identities
+        assert_eq!(ids.len(), 2);
+        assert!(ids.exist(a.clone()) && ids.exist(a2));
+        assert_eq!(ids.insert(a.clone()).len(), 2);
+        assert!(!ids.exist(Identity::new(String::from("a"))));
+        assert_eq!(*a, "a");
+
+        let values = HashSet::empty()
+            .insert(ByValue(String::from("a")))
+            .insert(ByValue(String::from("a")));
+        assert_eq!(values.len(), 1);
+        assert!(values.exist(ByValue(String::from("a"))));
+        assert!(!values.exist(ByValue(String::from("b"))));
+        assert_eq!(
+            Hashable::hash(&ByValue(String::from("a"))),
+            Hashable::hash(&ByValue(String::from("a")))
+        );
+    }
+
+    #[test]
+    fn clone_iterators() {
+        // clone the iterator after `n` elements: both copies yield the same remaining elements
+        fn check<I: Iterator + Clone>(mut iter: I, n: usize, len: usize)
+        where
+            I::Item: PartialEq + std::fmt::Debug,
+        {
+            for _ in 0..n {
+                iter.next().unwrap();
+            }
+            let bookmark = iter.clone();
+            let rest = iter.collect::<Vec<_>>();
+            assert_eq!(rest.len(), len - n);
+            assert_eq!(bookmark.collect::<Vec<_>>(), rest);
+        }
+
+        let mut l = List::empty();
+        let mut q = Queue::empty();
+        let mut d = Deque::empty();
+        let mut s = Set::empty();
+        let mut m = Map::empty();
+        let mut hs = HashSet::empty();
+        let mut hm = HashMap::empty();
+        let mut t = Path::new(0);
+        for i in 0..1000usize {
+            l = l.push(i);
+            q = q.enqueue(i);
+            d = d.push_back(i);
+            s = s.insert(i);
+            m = m.insert(i, i);
+            hs = hs.insert(i);
+            hm = hm.insert(i, i);
+            t = t.add_node(i + 1).parent();
+        }
+
+        for n in [0, 1, 500, 1000] {
+            check(l.iter(), n, 1000);
+            check(q.iter(), n, 1000);
+            check(d.iter(), n, 1000);
+            check(s.iter_ref(), n, 1000);
+            check(s.clone().into_iter(), n, 1000);
+            check(m.iter(), n, 1000);
+            check(m.iter_rev(), n, 1000);
+            check(hs.iter(), n, 1000);
+            check(hm.iter(), n, 1000);
+            check(t.iter_preorder().map(|p| *p.data()), n, 1001);
+            check(t.iter_postorder().map(|p| *p.data()), n, 1001);
+        }
+    }
+}
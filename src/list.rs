@@ -28,7 +28,9 @@ use std::marker::PhantomData;
 // ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE
 // POSSIBILITY OF SUCH DAMAGE.
 //
-use std::sync::Arc;
+use crate::Arc;
+
+use crate::queue::*;
 
 enum ListNode<E> {
     Nil,
@@ -88,6 +90,15 @@ fn to_vec<E: Clone>(l: &N<E>) -> Vec<E> {
     }
 }
 
+// inverse of to_vec: the first element of the vector ends up as the top of the list
+fn from_vec<E>(v: Vec<E>) -> N<E> {
+    let mut n = empty();
+    for e in v.into_iter().rev() {
+        n = push(&n, e);
+    }
+    n
+}
+
 fn rev<E: Clone>(l: &N<E>) -> N<E> {
     let mut n = empty();
     let mut s = l;
@@ -102,6 +113,51 @@ fn rev<E: Clone>(l: &N<E>) -> N<E> {
     }
 }
 
+// references to the (at most) `i` top elements, and the node following them
+fn split_at<E>(l: &N<E>, i: usize) -> (Vec<&E>, &N<E>) {
+    let mut prefix = Vec::with_capacity(usize::min(i, len(l)));
+    let mut n = l;
+    while prefix.len() < i {
+        match n.as_ref() {
+            Nil => break,
+            Node(_, e, next) => {
+                prefix.push(e);
+                n = next;
+            }
+        }
+    }
+    (prefix, n)
+}
+
+// push the prefix back on top of `tail`, the first element of the prefix ending up on top
+fn push_prefix<E: Clone>(prefix: Vec<&E>, tail: &N<E>) -> N<E> {
+    let mut n = tail.clone();
+    for e in prefix.into_iter().rev() {
+        n = push(&n, e.clone());
+    }
+    n
+}
+
+// fold the `n` top elements of `l` from the lowest one up, without collecting them: the lower
+// half is folded first, then the upper one. O(n log(n)) time, O(log(n)) stack
+fn fold_rev<'a, E, B, F: FnMut(B, &'a E) -> B>(l: &'a N<E>, n: usize, acc: B, f: &mut F) -> B {
+    match n {
+        0 => acc,
+        1 => f(acc, top(l)),
+        _ => {
+            let mut lower = l;
+            for _ in 0..n / 2 {
+                lower = match lower.as_ref() {
+                    Nil => unreachable!(),
+                    Node(_, _, next) => next,
+                };
+            }
+            let acc = fold_rev(lower, n - n / 2, acc, f);
+            fold_rev(l, n / 2, acc, f)
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct List<E: Clone + Sized> {
     n: N<E>,
@@ -115,6 +171,22 @@ impl<E: Clone + Sized> List<E> {
         Self { n: empty() }
     }
 
+    ///
+    /// create and return a new list/stack holding only the given element
+    ///
+    pub fn singleton(e: E) -> Self {
+        Self {
+            n: push(&empty(), e),
+        }
+    }
+
+    ///
+    /// create and return a new list/stack out of a vector (the first element of the vector being the top)
+    ///
+    pub(crate) fn from_vec(v: Vec<E>) -> Self {
+        Self { n: from_vec(v) }
+    }
+
     ///
     /// create and return a new list/stack with the new element added as the top element
     ///
@@ -167,17 +239,277 @@ impl<E: Clone + Sized> List<E> {
         List { n: rev(&self.n) }
     }
 
+    ///
+    /// build a queue out of the list/stack: the top element is the first to be dequeued (same order as `to_vec`)
+    ///
+    pub fn to_queue(&self) -> Queue<E> {
+        self.iter().collect()
+    }
+
+    ///
+    /// build a queue out of the list/stack: the bottom element is the first to be dequeued, that is
+    /// the elements are dequeued in the order they were pushed (reverse order of `to_vec`)
+    ///
+    pub fn to_queue_rev(&self) -> Queue<E> {
+        let mut v = self.to_vec();
+        v.reverse();
+        v.into_iter().collect()
+    }
+
+    ///
+    /// split the list/stack into the segments found between the elements for which `pred` returns
+    /// true (the separators are not part of any segment). Segments keep their top to bottom order,
+    /// adjacent separators produce empty segments, and an empty list yields a single empty segment
+    ///
+    pub fn split_when<F: Fn(&E) -> bool>(&self, pred: F) -> List<List<E>> {
+        let mut segments = Vec::new();
+        let mut current = Vec::new();
+        for e in self.iter() {
+            if pred(&e) {
+                segments.push(List {
+                    n: from_vec(std::mem::take(&mut current)),
+                });
+            } else {
+                current.push(e);
+            }
+        }
+        segments.push(List {
+            n: from_vec(current),
+        });
+        List {
+            n: from_vec(segments),
+        }
+    }
+
+    ///
+    /// split the list/stack (top to bottom) into consecutive lists/stacks of `n` elements, the last
+    /// one holding the remaining (at most `n`) elements and sharing them with `self`.
+    /// Panics if `n` is 0
+    ///
+    pub fn chunks(&self, n: usize) -> List<List<E>> {
+        assert!(n != 0, "chunks: the chunk size must be non-zero");
+        let mut chunks = Vec::new();
+        let mut node = &self.n;
+        while len(node) > n {
+            let (prefix, tail) = split_at(node, n);
+            chunks.push(List {
+                n: push_prefix(prefix, &empty()),
+            });
+            node = tail;
+        }
+        if len(node) > 0 {
+            chunks.push(List { n: node.clone() });
+        }
+        List::from_vec(chunks)
+    }
+
+    ///
+    /// returns an iterator over the windows of `size` consecutive elements (top to bottom), each window
+    /// starting one element below the previous one. A list/stack shorter than `size` yields no window.
+    /// Panics if `size` is 0
+    ///
+    pub fn windows(&self, size: usize) -> impl Iterator<Item = Vec<E>> + '_ {
+        assert!(size != 0, "windows: the window size must be non-zero");
+        let mut n = &self.n;
+        std::iter::from_fn(move || match n.as_ref() {
+            Node(s, _, next) if *s >= size => {
+                let (window, _) = split_at(n, size);
+                n = next;
+                Some(window.into_iter().cloned().collect())
+            }
+            _ => None,
+        })
+    }
+
+    ///
+    /// split the list/stack in two: the elements for which `f` returns true, and the others.
+    /// Both lists/stacks keep the top to bottom order of the elements
+    ///
+    pub fn partition<F: Fn(&E) -> bool>(&self, f: F) -> (List<E>, List<E>) {
+        let (a, b): (Vec<E>, Vec<E>) = self.iter_ref().cloned().partition(|e| f(e));
+        (List::from_vec(a), List::from_vec(b))
+    }
+
+    ///
+    /// split the list/stack into groups of consecutive elements sharing the same `key` (top to bottom).
+    /// Groups keep their top to bottom order, and an empty list yields no group
+    ///
+    pub fn group_by<K: PartialEq, F: FnMut(&E) -> K>(&self, mut key: F) -> List<List<E>> {
+        let mut groups = Vec::new();
+        let mut current: Option<(K, Vec<E>)> = None;
+        for e in self.iter_ref() {
+            let k = key(e);
+            match &mut current {
+                Some((ck, group)) if *ck == k => group.push(e.clone()),
+                _ => {
+                    if let Some((_, group)) = current.replace((k, vec![e.clone()])) {
+                        groups.push(List::from_vec(group));
+                    }
+                }
+            }
+        }
+        if let Some((_, group)) = current {
+            groups.push(List::from_vec(group));
+        }
+        List::from_vec(groups)
+    }
+
+    ///
+    /// returns an iterator over references to the elements (top element first)
+    ///
+    pub(crate) fn iter_ref(&self) -> impl Iterator<Item = &E> + '_ {
+        let mut n = &self.n;
+        std::iter::from_fn(move || match n.as_ref() {
+            Nil => None,
+            Node(_, e, l) => {
+                n = l;
+                Some(e)
+            }
+        })
+    }
+
+    ///
+    /// fold references to the elements from the bottom element to the top one, without collecting
+    /// them: O(n log(n))
+    ///
+    pub(crate) fn fold_rev_ref<'a, B, F: FnMut(B, &'a E) -> B>(&'a self, init: B, mut f: F) -> B {
+        fold_rev(&self.n, self.len(), init, &mut f)
+    }
+
+    ///
+    /// create and return a list/stack of pairs out of the elements of both lists/stacks (top elements first),
+    /// stopping at the end of the shorter one
+    ///
+    pub fn zip<B: Clone>(&self, other: &List<B>) -> List<(E, B)> {
+        List::from_vec(
+            self.iter_ref()
+                .zip(other.iter_ref())
+                .map(|(a, b)| (a.clone(), b.clone()))
+                .collect(),
+        )
+    }
+
+    ///
+    /// create and return the list/stack of the accumulators obtained after each element (top element
+    /// first): the top of the result is `f(&init, top)`. The initial value itself is not part of it
+    ///
+    pub fn scan<B: Clone, F: FnMut(&B, &E) -> B>(&self, init: B, mut f: F) -> List<B> {
+        let mut acc = init;
+        let mut v = Vec::with_capacity(self.len());
+        for e in self.iter_ref() {
+            acc = f(&acc, e);
+            v.push(acc.clone());
+        }
+        List::from_vec(v)
+    }
+
+    ///
+    /// create and return the concatenation of the lists/stacks: the elements of `lists[0]` come first
+    /// (on top), followed by those of `lists[1]`, ... The last list is shared as the tail, the others are copied
+    ///
+    pub fn concat(lists: &[List<E>]) -> Self {
+        match lists.split_last() {
+            None => Self::empty(),
+            Some((last, rest)) => {
+                let mut n = last.n.clone();
+                for l in rest.iter().rev() {
+                    let v = l.iter_ref().collect::<Vec<_>>();
+                    for e in v.into_iter().rev() {
+                        n = push(&n, e.clone());
+                    }
+                }
+                Self { n }
+            }
+        }
+    }
+
+    ///
+    /// create and return the concatenation of the lists/stacks returned by `f` for each element
+    /// (top element first), as `concat` does
+    ///
+    pub fn flat_map<W: Clone, F: FnMut(&E) -> List<W>>(&self, f: F) -> List<W> {
+        List::concat(&self.iter_ref().map(f).collect::<Vec<_>>())
+    }
+
+    ///
+    /// returns the bottom element (the first pushed), None if the list/stack is empty.
+    /// The whole list/stack is walked: O(n)
+    ///
+    pub fn last(&self) -> Option<&E> {
+        self.iter_ref().last()
+    }
+
+    ///
+    /// returns the element at index `i` counting from the bottom (0 being the bottom element),
+    /// None if out of bounds: O(n - i)
+    ///
+    pub fn nth_from_bottom(&self, i: usize) -> Option<&E> {
+        match i < self.len() {
+            true => self.iter_ref().nth(self.len() - 1 - i),
+            false => None,
+        }
+    }
+
+    ///
+    /// create and return a new list/stack with `e` inserted at index `i` (0 being the top). The `i`
+    /// elements above it are copied, the rest is shared: O(i). An index past the end (`i > len`)
+    /// is clamped: `e` becomes the bottom element
+    ///
+    pub fn insert_at(&self, i: usize, e: E) -> Self {
+        let (prefix, tail) = split_at(&self.n, i);
+        Self {
+            n: push_prefix(prefix, &push(tail, e)),
+        }
+    }
+
+    ///
+    /// create and return a new list/stack with the element at index `i` (0 being the top) removed.
+    /// The `i` elements above it are copied, the rest is shared: O(i). An out of bounds index
+    /// returns the list/stack unchanged
+    ///
+    pub fn remove_at(&self, i: usize) -> Self {
+        let (prefix, tail) = split_at(&self.n, i);
+        match tail.as_ref() {
+            Nil => self.clone(),
+            Node(_, _, next) => Self {
+                n: push_prefix(prefix, next),
+            },
+        }
+    }
+
+    ///
+    /// returns an iterator from the bottom element to the top one. The references to the elements
+    /// are collected first: O(n) extra space
+    ///
+    pub fn iter_rev(&self) -> impl DoubleEndedIterator<Item = E> + '_ {
+        self.iter_ref()
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .cloned()
+    }
+
     ///
     /// returns an iterator
     ///
     pub fn iter<'a>(&self) -> Iter<'a, E> {
         Iter {
             node: self.n.clone(),
-            _phantom: PhantomData::default(),
+            _phantom: PhantomData,
         }
     }
 }
 
+impl<A: Clone, B: Clone> List<(A, B)> {
+    ///
+    /// split a list/stack of pairs into two lists/stacks, preserving the order
+    ///
+    pub fn unzip(&self) -> (List<A>, List<B>) {
+        let (a, b): (Vec<A>, Vec<B>) = self.iter().unzip();
+        (List::from_vec(a), List::from_vec(b))
+    }
+}
+
 fn drop_next<E>(n: &mut N<E>) -> Option<N<E>> {
     let mv = N::get_mut(n);
     match mv {
@@ -205,6 +537,7 @@ impl<E: Clone> Drop for List<E> {
     }
 }
 
+#[derive(Clone)]
 pub struct Iter<'a, E> {
     node: N<E>,
     _phantom: PhantomData<&'a E>,
@@ -293,6 +626,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::explicit_counter_loop)]
     fn iter() {
         let mut elements = Vec::new();
         let mut l = List::empty();
@@ -311,4 +645,370 @@ mod tests {
             count += 1;
         }
     }
+
+    #[test]
+    fn split_when() {
+        // top to bottom: 1 2 0 3 0 0 4 5
+        let mut l = List::empty();
+        for e in [5, 4, 0, 0, 3, 0, 2, 1] {
+            l = l.push(e);
+        }
+
+        let segments = l.split_when(|e| *e == 0);
+        assert_eq!(segments.len(), 4);
+
+        let v = segments.iter().map(|s| s.to_vec()).collect::<Vec<_>>();
+        assert_eq!(v, vec![vec![1, 2], vec![3], vec![], vec![4, 5]]);
+        for s in segments.iter() {
+            assert_eq!(s.len(), s.to_vec().len());
+        }
+
+        let segments = List::empty().push(0).split_when(|e| *e == 0);
+        assert_eq!(segments.len(), 2);
+        assert!(segments.iter().all(|s| s.is_empty()));
+
+        let segments = List::<i32>::empty().split_when(|e| *e == 0);
+        assert_eq!(segments.len(), 1);
+        assert!(segments.top().is_empty());
+    }
+
+    #[test]
+    fn to_queue() {
+        let mut l = List::empty();
+        for i in 0..100 {
+            l = l.push(i);
+        }
+
+        let q = l.to_queue();
+        assert_eq!(q.len(), l.len());
+        assert_eq!(q.to_vec(), l.to_vec());
+
+        let q = l.to_queue_rev();
+        assert_eq!(q.len(), l.len());
+        let mut v = l.to_vec();
+        v.reverse();
+        assert_eq!(q.to_vec(), v);
+        assert_eq!(q.front(), Some(&0));
+
+        assert!(List::<i32>::empty().to_queue_rev().is_empty());
+    }
+
+    #[test]
+    fn unzip() {
+        let mut a = List::empty();
+        let mut b = List::empty();
+        for i in 0..100 {
+            a = a.push(i);
+            b = b.push(format!("{}", i));
+        }
+
+        let mut pairs = List::empty();
+        for p in a.to_vec().into_iter().zip(b.to_vec()).rev() {
+            pairs = pairs.push(p);
+        }
+
+        let (ua, ub) = pairs.unzip();
+        assert_eq!(ua.len(), 100);
+        assert_eq!(ub.len(), 100);
+        assert_eq!(ua.to_vec(), a.to_vec());
+        assert_eq!(ub.to_vec(), b.to_vec());
+
+        let (ea, eb) = List::<(i32, i32)>::empty().unzip();
+        assert!(ea.is_empty() && eb.is_empty());
+    }
+
+    #[test]
+    fn iter_rev() {
+        let mut l = List::empty();
+        assert!(l.last().is_none());
+        assert_eq!(l.iter_rev().count(), 0);
+        assert!(l.nth_from_bottom(0).is_none());
+
+        for _ in 0..1000 {
+            l = l.push(rand());
+        }
+
+        let mut expected = l.to_vec();
+        expected.reverse();
+        assert_eq!(l.iter_rev().collect::<Vec<_>>(), expected);
+        assert_eq!(l.iter_rev().rev().collect::<Vec<_>>(), l.to_vec());
+        assert_eq!(l.last(), Some(&expected[0]));
+        for (i, e) in expected.iter().enumerate() {
+            assert_eq!(l.nth_from_bottom(i), Some(e));
+        }
+        assert!(l.nth_from_bottom(1000).is_none());
+
+        let l = l.pop();
+        assert_eq!(l.last(), Some(&expected[0]));
+        assert_eq!(l.iter_rev().last(), Some(expected[998]));
+    }
+
+    #[test]
+    fn singleton() {
+        let l = List::singleton(3);
+        assert_eq!(l.len(), 1);
+        assert_eq!(*l.top(), 3);
+        assert_eq!(l.push(4).to_vec(), vec![4, 3]);
+        assert!(l.pop().is_empty());
+    }
+
+    #[test]
+    fn concat() {
+        let mut lists = Vec::new();
+        let mut vecs = Vec::new();
+        for i in 0..10 {
+            let mut l = List::empty();
+            for _ in 0..(i * 7 % 5) {
+                l = l.push(rand());
+            }
+            vecs.push(l.to_vec());
+            lists.push(l);
+        }
+
+        let c = List::concat(&lists);
+        assert_eq!(c.to_vec(), vecs.concat());
+        assert_eq!(c.len(), vecs.concat().len());
+        assert_eq!(c.iter().count(), c.len());
+
+        // the last list is shared
+        let c = List::concat(&[List::singleton(1), List::singleton(2), lists[9].clone()]);
+        assert_eq!(c.len(), lists[9].len() + 2);
+        assert!(Arc::ptr_eq(&c.pop().pop().n, &lists[9].n));
+
+        assert!(List::<i32>::concat(&[]).is_empty());
+        assert!(List::<i32>::concat(&[List::empty(), List::empty()]).is_empty());
+    }
+
+    #[test]
+    fn flat_map() {
+        let mut l = List::empty();
+        for _ in 0..100 {
+            l = l.push(rand() % 10);
+        }
+
+        let f = |e: &i32| {
+            let mut r = List::empty();
+            for i in 0..*e {
+                r = r.push(i);
+            }
+            r
+        };
+        let expected = l
+            .to_vec()
+            .iter()
+            .flat_map(|e| f(e).to_vec())
+            .collect::<Vec<_>>();
+        let fm = l.flat_map(f);
+        assert_eq!(fm.to_vec(), expected);
+        assert_eq!(fm.len(), expected.len());
+        assert!(List::<i32>::empty()
+            .flat_map(|e| List::singleton(*e))
+            .is_empty());
+    }
+
+    #[test]
+    fn zip() {
+        let mut a = List::empty();
+        let mut b = List::empty();
+        for i in 0..100 {
+            a = a.push(rand());
+            b = b.push(i);
+        }
+
+        for (x, y) in [
+            (a.clone(), b.clone()),
+            (a.pop().pop(), b.clone()),
+            (a.clone(), b.pop()),
+        ] {
+            let z = x.zip(&y);
+            let expected = x.to_vec().into_iter().zip(y.to_vec()).collect::<Vec<_>>();
+            assert_eq!(z.len(), expected.len());
+            assert_eq!(z.to_vec(), expected);
+            assert_eq!(z.unzip().0.to_vec(), x.to_vec()[..z.len()].to_vec());
+        }
+        assert!(a.zip(&List::<i32>::empty()).is_empty());
+    }
+
+    #[test]
+    fn insert_at_remove_at() {
+        let mut l = List::empty();
+        for _ in 0..100 {
+            l = l.push(rand());
+        }
+        let v = l.to_vec();
+
+        for i in [0, 1, 50, 99, 100] {
+            let l2 = l.insert_at(i, 7);
+            let mut expected = v.clone();
+            expected.insert(i, 7);
+            assert_eq!(l2.len(), 101);
+            assert_eq!(l2.to_vec(), expected);
+            assert_eq!(l2.remove_at(i).to_vec(), v);
+        }
+
+        for i in [0, 1, 50, 99] {
+            let l2 = l.remove_at(i);
+            let mut expected = v.clone();
+            expected.remove(i);
+            assert_eq!(l2.len(), 99);
+            assert_eq!(l2.to_vec(), expected);
+            assert_eq!(l2.pop().len(), 98);
+        }
+
+        // out of range
+        assert_eq!(l.insert_at(1000, 7).to_vec()[100], 7);
+        assert_eq!(l.insert_at(1000, 7).last(), Some(&7));
+        assert_eq!(l.remove_at(100).to_vec(), v);
+        assert!(List::<i32>::empty().remove_at(0).is_empty());
+        assert_eq!(List::empty().insert_at(3, 1).to_vec(), vec![1]);
+    }
+
+    #[test]
+    fn group_by() {
+        let v = vec![1, 2, 3, 4, 6, 8, 5, 7, 9, 11, 10, 1, 3];
+        let l = List::from_vec(v.clone());
+        let groups = l.group_by(|e| e % 2);
+
+        let expected: Vec<Vec<i32>> = vec![
+            vec![1],
+            vec![2],
+            vec![3],
+            vec![4, 6, 8],
+            vec![5, 7, 9, 11],
+            vec![10],
+            vec![1, 3],
+        ];
+        assert_eq!(groups.len(), expected.len());
+        for (g, e) in groups.iter().zip(expected.iter()) {
+            assert_eq!(g.len(), e.len());
+            assert_eq!(&g.to_vec(), e);
+        }
+        assert_eq!(
+            groups.iter().flat_map(|g| g.to_vec()).collect::<Vec<_>>(),
+            v
+        );
+
+        assert_eq!(l.group_by(|_| ()).len(), 1);
+        assert_eq!(l.group_by(|_| ()).top().len(), v.len());
+        assert!(List::<i32>::empty().group_by(|e| e % 2).is_empty());
+    }
+
+    #[test]
+    fn scan() {
+        let mut l = List::empty();
+        for _ in 0..1000 {
+            l = l.push(rand() as i64);
+        }
+
+        let sums = l.scan(0i64, |acc, e| acc + e);
+        let mut expected = Vec::new();
+        let mut sum = 0;
+        for e in l.to_vec() {
+            sum += e;
+            expected.push(sum);
+        }
+        assert_eq!(sums.len(), 1000);
+        assert_eq!(sums.to_vec(), expected);
+        assert_eq!(sums.pop().len(), 999);
+        assert_eq!(sums.last(), Some(&l.iter().sum::<i64>()));
+
+        let strs =
+            List::from_vec(vec![1, 2, 3]).scan(String::new(), |acc, e| format!("{}{}", acc, e));
+        assert_eq!(strs.to_vec(), vec!["1", "12", "123"]);
+        assert!(List::<i32>::empty().scan(0, |a, e| a + e).is_empty());
+    }
+
+    #[test]
+    fn partition() {
+        let mut l = List::empty();
+        for _ in 0..1000 {
+            l = l.push(rand());
+        }
+
+        let (even, odd) = l.partition(|e| e % 2 == 0);
+        assert_eq!(even.len() + odd.len(), l.len());
+        assert!(even.iter().all(|e| e % 2 == 0));
+        assert!(odd.iter().all(|e| e % 2 != 0));
+        assert_eq!(even.pop().len(), even.len() - 1);
+
+        // merging back by the original positions gives the original order
+        let (mut a, mut b) = (even.iter(), odd.iter());
+        let merged = l
+            .iter()
+            .map(|e| match e % 2 == 0 {
+                true => a.next().unwrap(),
+                false => b.next().unwrap(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(merged, l.to_vec());
+        assert!(a.next().is_none() && b.next().is_none());
+
+        let (all, none) = l.partition(|_| true);
+        assert_eq!(all.to_vec(), l.to_vec());
+        assert!(none.is_empty());
+        let (x, y) = List::<i32>::empty().partition(|_| true);
+        assert!(x.is_empty() && y.is_empty());
+    }
+
+    #[test]
+    fn windows() {
+        let l = List::from_vec(vec![1, 2, 3, 4, 5]);
+        assert_eq!(
+            l.windows(2).collect::<Vec<_>>(),
+            vec![vec![1, 2], vec![2, 3], vec![3, 4], vec![4, 5]]
+        );
+        assert_eq!(
+            l.windows(3).collect::<Vec<_>>(),
+            vec![vec![1, 2, 3], vec![2, 3, 4], vec![3, 4, 5]]
+        );
+        assert_eq!(l.windows(1).count(), 5);
+        assert_eq!(l.windows(5).collect::<Vec<_>>(), vec![vec![1, 2, 3, 4, 5]]);
+        assert_eq!(l.windows(6).count(), 0);
+        assert_eq!(List::<i32>::empty().windows(1).count(), 0);
+
+        let v = (0..100).collect::<Vec<_>>();
+        let l = List::from_vec(v.clone());
+        assert!(l.windows(7).eq(v.windows(7).map(|w| w.to_vec())));
+    }
+
+    #[test]
+    #[should_panic]
+    fn windows_zero() {
+        let _ = List::singleton(1).windows(0);
+    }
+
+    #[test]
+    fn chunks() {
+        let v = (0..1000).collect::<Vec<_>>();
+        let l = List::from_vec(v.clone());
+
+        let chunks = l.chunks(100);
+        assert_eq!(chunks.len(), 10);
+        for (i, c) in chunks.iter().enumerate() {
+            assert_eq!(c.len(), 100);
+            assert_eq!(c.to_vec(), v[i * 100..(i + 1) * 100].to_vec());
+            assert_eq!(c.pop().len(), 99);
+        }
+
+        let chunks = l.chunks(300).to_vec();
+        assert_eq!(
+            chunks.iter().map(|c| c.len()).collect::<Vec<_>>(),
+            vec![300, 300, 300, 100]
+        );
+        assert_eq!(
+            chunks.iter().flat_map(|c| c.to_vec()).collect::<Vec<_>>(),
+            v
+        );
+
+        assert_eq!(l.chunks(1000).len(), 1);
+        assert_eq!(l.chunks(5000).top().len(), 1000);
+        assert_eq!(l.chunks(1).len(), 1000);
+        assert!(List::<i32>::empty().chunks(3).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn chunks_zero() {
+        List::singleton(1).chunks(0);
+    }
 }
@@ -28,7 +28,12 @@
 // POSSIBILITY OF SUCH DAMAGE.
 //
 
-use std::sync::Arc;
+use crate::Arc;
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+
+use crate::set::*;
+use crate::{HashMap, Hashable};
 
 #[derive(Clone)]
 enum MapNode<K: Clone, V: Clone> {
@@ -133,6 +138,56 @@ impl<K: Ord + Clone, V: Clone> MapNode<K, V> {
         }
     }
 
+    // AVL join: all the keys of `l` < `k` < all the keys of `r`. Descends the taller tree along
+    // its inner spine until the heights are close enough: O(|h(l) - h(r)|)
+    fn join(l: &N<K, V>, k: K, v: V, r: &N<K, V>) -> N<K, V> {
+        match (l.as_ref(), r.as_ref()) {
+            (Empty, _) => S::insert(r, k, v),
+            (_, Empty) => S::insert(l, k, v),
+            (Node(lh, ll, lk, lv, lr), _) if *lh > r.height() + 2 => {
+                S::rebalance(ll, lk.clone(), lv.clone(), &S::join(lr, k, v, r))
+            }
+            (_, Node(rh, rl, rk, rv, rr)) if *rh > l.height() + 2 => {
+                S::rebalance(&S::join(l, k, v, rl), rk.clone(), rv.clone(), rr)
+            }
+            _ => S::make(l, k, v, r),
+        }
+    }
+
+    // returns the existing value (Err), or inserts `default()` and returns it with the new tree (Ok)
+    fn entry_or<F: FnOnce() -> V>(t: &N<K, V>, k: K, default: F) -> Result<(V, N<K, V>), V> {
+        match t.as_ref() {
+            Node(_, l, k2, v2, r) if k < *k2 => S::entry_or(l, k, default)
+                .map(|(v, l2)| (v, S::rebalance(&l2, k2.clone(), v2.clone(), r))),
+            Node(_, _, k2, v2, _) if k == *k2 => Err(v2.clone()),
+            Node(_, l, k2, v2, r) => S::entry_or(r, k, default)
+                .map(|(v, r2)| (v, S::rebalance(l, k2.clone(), v2.clone(), &r2))),
+            One(k2, v2) if k == *k2 => Err(v2.clone()),
+            One(_, _) | Empty => {
+                let v = default();
+                Ok((v.clone(), S::insert(t, k, v)))
+            }
+        }
+    }
+
+    // replaces the value of `k` with `update(old)`, or inserts `default`. Returns the new tree, and
+    // true if the key was inserted
+    fn upsert<F: FnOnce(&V) -> V>(t: &N<K, V>, k: K, default: V, update: F) -> (N<K, V>, bool) {
+        match t.as_ref() {
+            Node(_, l, k2, v2, r) if k < *k2 => {
+                let (l2, inserted) = S::upsert(l, k, default, update);
+                (S::rebalance(&l2, k2.clone(), v2.clone(), r), inserted)
+            }
+            Node(h, l, k2, v2, r) if k == *k2 => (S::node(*h, l, k2.clone(), update(v2), r), false),
+            Node(_, l, k2, v2, r) => {
+                let (r2, inserted) = S::upsert(r, k, default, update);
+                (S::rebalance(l, k2.clone(), v2.clone(), &r2), inserted)
+            }
+            One(k2, v2) if k == *k2 => (S::one(k2.clone(), update(v2)), false),
+            One(_, _) | Empty => (S::insert(t, k, default), true),
+        }
+    }
+
     fn splice_out_successor(t: &N<K, V>) -> (K, V, N<K, V>) {
         match t.as_ref() {
             Empty => panic!("internal error"),
@@ -178,15 +233,64 @@ impl<K: Ord + Clone, V: Clone> MapNode<K, V> {
         }
     }
 
-    fn find(&self, k: K) -> Option<&V> {
+    fn find(&self, k: &K) -> Option<&V> {
         match self {
             Empty => None,
-            One(k2, v) if k == k2.clone() => Some(v),
+            One(k2, v) if k == k2 => Some(v),
             One(_, _) => None,
-            Node(_, l, k2, _, _) if k < k2.clone() => S::find(l, k),
-            Node(_, _, k2, v, _) if k == k2.clone() => Some(v),
-            Node(_, _, k2, _, r) if k > k2.clone() => S::find(r, k),
-            _ => unreachable!(),
+            Node(_, l, k2, _, _) if k < k2 => S::find(l, k),
+            Node(_, _, k2, v, _) if k == k2 => Some(v),
+            Node(_, _, _, _, r) => S::find(r, k),
+        }
+    }
+
+    ///
+    /// build a balanced tree out of the next `n` elements of an ascending iterator
+    ///
+    fn from_sorted_iter<I: Iterator<Item = (K, V)>>(n: usize, iter: &mut I) -> N<K, V> {
+        match n {
+            0 => S::empty(),
+            _ => {
+                let l = S::from_sorted_iter(n / 2, iter);
+                let (k, v) = iter.next().unwrap();
+                let r = S::from_sorted_iter(n - n / 2 - 1, iter);
+                S::make(&l, k, v, &r)
+            }
+        }
+    }
+
+    ///
+    /// rebuild the same tree shape with the keys mapped through a strictly monotonic function
+    /// (the keys are visited in order, to check the monotonicity in debug builds)
+    ///
+    fn map_keys_monotonic<K2: Ord + Clone, F: FnMut(&K) -> K2>(
+        t: &N<K, V>,
+        f: &mut F,
+        last: &mut Option<K2>,
+    ) -> N<K2, V> {
+        fn map_key<K, K2: Ord + Clone, F: FnMut(&K) -> K2>(
+            k: &K,
+            f: &mut F,
+            last: &mut Option<K2>,
+        ) -> K2 {
+            let k2 = f(k);
+            debug_assert!(
+                last.as_ref().is_none_or(|l| *l < k2),
+                "map_keys_monotonic: the key mapping is not strictly monotonic"
+            );
+            *last = Some(k2.clone());
+            k2
+        }
+
+        match t.as_ref() {
+            Empty => MapNode::empty(),
+            One(k, v) => MapNode::one(map_key(k, f, last), v.clone()),
+            Node(h, l, k, v, r) => {
+                let l2 = S::map_keys_monotonic(l, f, last);
+                let k2 = map_key(k, f, last);
+                let r2 = S::map_keys_monotonic(r, f, last);
+                MapNode::node(*h, &l2, k2, v.clone(), &r2)
+            }
         }
     }
 
@@ -211,13 +315,37 @@ impl<K: Clone, V: Clone> MapNode<K, V> {
             Node(h, _, _, _, _) => *h,
         }
     }
+
+    fn node_count(&self) -> usize {
+        match self {
+            Empty => 0,
+            One(_, _) => 1,
+            Node(_, l, _, _, r) => 1 + l.node_count() + r.node_count(),
+        }
+    }
 }
 
+///
+/// cloning a map is O(1): the clone shares the tree
+///
+#[derive(Clone)]
 pub struct Map<K: Ord + Clone, V: Clone> {
     size: usize,
     n: N<K, V>,
 }
 
+impl<K: Ord + Clone, V: Clone> From<std::collections::BTreeMap<K, V>> for Map<K, V> {
+    fn from(m: std::collections::BTreeMap<K, V>) -> Self {
+        Self::from_sorted_vec(m.into_iter().collect())
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Default for Map<K, V> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
 impl<K: Ord + Clone, V: Clone> Map<K, V> {
     ///
     /// create and return a new empty map
@@ -229,6 +357,16 @@ impl<K: Ord + Clone, V: Clone> Map<K, V> {
         }
     }
 
+    ///
+    /// create and return a new map containing only the given key, value pair
+    ///
+    pub fn singleton(k: K, v: V) -> Self {
+        Self {
+            n: S::one(k, v),
+            size: 1,
+        }
+    }
+
     ///
     /// create and return a new map containing the new key, value pair
     ///
@@ -239,11 +377,51 @@ impl<K: Ord + Clone, V: Clone> Map<K, V> {
         }
     }
 
+    ///
+    /// return the value of `k` if it exists (the map being unchanged), otherwise insert `default()`
+    /// and return it along with the new map. The tree is walked once
+    ///
+    pub fn entry_or<F: FnOnce() -> V>(&self, k: K, default: F) -> (V, Self) {
+        match S::entry_or(&self.n, k, default) {
+            Ok((v, n)) => (
+                v,
+                Self {
+                    n,
+                    size: self.size + 1,
+                },
+            ),
+            Err(v) => (v, self.clone()),
+        }
+    }
+
+    ///
+    /// create and return a new map where the value of `k` is replaced with `update(old)`, or where
+    /// `k` is inserted with `default` if it doesn't exist. The tree is walked once
+    ///
+    pub fn upsert<F: FnOnce(&V) -> V>(&self, k: K, default: V, update: F) -> Self {
+        let (n, inserted) = S::upsert(&self.n, k, default, update);
+        Self {
+            n,
+            size: self.size + inserted as usize,
+        }
+    }
+
+    ///
+    /// create and return a new map with `by` added to the value of `k` (inserted as `by` if it
+    /// doesn't exist), as `upsert` does
+    ///
+    pub fn increment(&self, k: K, by: V) -> Self
+    where
+        V: std::ops::Add<Output = V>,
+    {
+        self.upsert(k, by.clone(), |v| v.clone() + by)
+    }
+
     ///
     /// create and return a new map with the key, value pair removed
     ///
     pub fn remove(&self, k: K) -> Self {
-        let size = match S::find(&self.n, k.clone()) {
+        let size = match S::find(&self.n, &k) {
             Some(_) => self.size - 1,
             None => self.size,
         };
@@ -251,18 +429,59 @@ impl<K: Ord + Clone, V: Clone> Map<K, V> {
         Self { n, size }
     }
 
+    ///
+    /// remove the keys of `iter` (with their values) and return the new map. The batch is sorted,
+    /// then merged with the entries of the map to rebuild the tree once: O(m log(m) + n). The map
+    /// is returned as is if none of the keys exist
+    ///
+    pub fn remove_all<I: IntoIterator<Item = K>>(&self, iter: I) -> Self {
+        let mut batch = iter.into_iter().collect::<Vec<_>>();
+        batch.sort();
+        batch.dedup();
+        let mut b = batch.iter().peekable();
+        let kept = self
+            .iter_ref()
+            .filter(|(k, _)| {
+                while b.next_if(|x| x < k).is_some() {}
+                b.next_if(|x| x == k).is_none()
+            })
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<Vec<_>>();
+        match kept.len() == self.size {
+            true => self.clone(),
+            false => Map::from_sorted_vec(kept),
+        }
+    }
+
     ///
     /// search for a key and return true if the key exist, false otherwise
     ///
     pub fn exist(&self, k: K) -> bool {
-        S::find(&self.n, k).is_some()
+        S::find(&self.n, &k).is_some()
     }
 
     ///
     /// search for a key and return a pointer to the value if the key exists, None otherwise
     ///
     pub fn find(&self, k: K) -> Option<&V> {
-        S::find(&self.n, k)
+        S::find(&self.n, &k)
+    }
+
+    ///
+    /// return true if the key exists, as `exist` does, taking the key by reference
+    ///
+    pub fn contains_key(&self, k: &K) -> bool {
+        S::find(&self.n, k).is_some()
+    }
+
+    ///
+    /// return true if an entry holds a value equal to `v`. The values aren't indexed: O(n)
+    ///
+    pub fn contains_value(&self, v: &V) -> bool
+    where
+        V: PartialEq,
+    {
+        self.iter_ref().any(|(_, x)| x == v)
     }
 
     ///
@@ -281,6 +500,13 @@ impl<K: Ord + Clone, V: Clone> Map<K, V> {
         self.n.height()
     }
 
+    ///
+    /// return the number of tree nodes, for debugging memory use: maps sharing nodes count them each
+    ///
+    pub fn node_count(&self) -> usize {
+        self.n.node_count()
+    }
+
     ///
     /// return true if the map is empty
     ///
@@ -294,6 +520,412 @@ impl<K: Ord + Clone, V: Clone> Map<K, V> {
     pub fn len(&self) -> usize {
         self.size
     }
+
+    ///
+    /// returns an iterator (ascending key order)
+    ///
+    pub fn iter<'a>(&self) -> MapIter<'a, K, V> {
+        let mut iter = MapIter {
+            stack: Vec::new(),
+            _phantom: PhantomData,
+        };
+        iter.push_left(&self.n);
+        iter
+    }
+
+    ///
+    /// returns an iterator over the entries whose keys are >= `start` (ascending key order). The
+    /// traversal is seeded along the search path to `start`: O(log n) to set up
+    ///
+    pub fn iter_from<'a>(&self, start: &K) -> MapIter<'a, K, V> {
+        let mut iter = MapIter {
+            stack: Vec::new(),
+            _phantom: PhantomData,
+        };
+        iter.push_from(&self.n, start);
+        iter
+    }
+
+    ///
+    /// returns an iterator (descending key order)
+    ///
+    pub fn iter_rev<'a>(&self) -> MapRevIter<'a, K, V> {
+        let mut iter = MapRevIter {
+            stack: Vec::new(),
+            _phantom: PhantomData,
+        };
+        iter.push_right(&self.n);
+        iter
+    }
+
+    ///
+    /// create and return the map holding the entries of `left`, the `pivot` entry and the entries of
+    /// `right`. All the keys of `left` must be smaller than the pivot key, itself smaller than all the
+    /// keys of `right` (as for the outputs of a split): O(|left.height() - right.height()|)
+    ///
+    pub fn join_with_pivot(left: &Map<K, V>, pivot: (K, V), right: &Map<K, V>) -> Self {
+        debug_assert!(
+            left.iter_rev().next().is_none_or(|(k, _)| k < pivot.0)
+                && right.iter().next().is_none_or(|(k, _)| k > pivot.0),
+            "join_with_pivot: the keys of left < pivot < the keys of right"
+        );
+        Self {
+            n: S::join(&left.n, pivot.0, pivot.1, &right.n),
+            size: left.size + 1 + right.size,
+        }
+    }
+
+    ///
+    /// inner join: create and return a new map holding the keys present in both maps,
+    /// paired with their values from each map. Walks both maps once: O(m + n)
+    ///
+    pub fn join<W: Clone>(&self, other: &Map<K, W>) -> Map<K, (V, W)> {
+        self.zip_values(other, |v, w| (v.clone(), w.clone()))
+    }
+
+    ///
+    /// create and return a new map holding the keys present in both maps, each with `f` applied to
+    /// its values from each map. Walks both maps once: O(m + n)
+    ///
+    pub fn zip_values<W: Clone, X: Clone, F: Fn(&V, &W) -> X>(
+        &self,
+        other: &Map<K, W>,
+        f: F,
+    ) -> Map<K, X> {
+        let mut res = Vec::new();
+        let mut a = self.iter_ref();
+        let mut b = other.iter_ref();
+        let mut ea = a.next();
+        let mut eb = b.next();
+        while let (Some((ka, va)), Some((kb, wb))) = (ea, eb) {
+            if ka < kb {
+                ea = a.next();
+            } else if ka > kb {
+                eb = b.next();
+            } else {
+                res.push((ka.clone(), f(va, wb)));
+                ea = a.next();
+                eb = b.next();
+            }
+        }
+        Map::from_sorted_vec(res)
+    }
+
+    ///
+    /// fold the values in ascending key order, without cloning the keys
+    ///
+    pub fn fold_values<B, F: FnMut(B, &V) -> B>(&self, init: B, mut f: F) -> B {
+        self.iter_ref().fold(init, |b, (_, v)| f(b, v))
+    }
+
+    ///
+    /// return the sum of the values
+    ///
+    pub fn sum_values(&self) -> V
+    where
+        V: std::iter::Sum,
+    {
+        self.iter_ref().map(|(_, v)| v.clone()).sum()
+    }
+
+    ///
+    /// returns the entry holding the greatest value, None if the map is empty. On ties, the entry
+    /// with the smallest key wins. The values aren't indexed: O(n)
+    ///
+    pub fn max_by_value(&self) -> Option<(&K, &V)>
+    where
+        V: Ord,
+    {
+        self.max_by_value_by(|a, b| a.cmp(b))
+    }
+
+    ///
+    /// returns the entry holding the smallest value, None if the map is empty. On ties, the entry
+    /// with the smallest key wins. The values aren't indexed: O(n)
+    ///
+    pub fn min_by_value(&self) -> Option<(&K, &V)>
+    where
+        V: Ord,
+    {
+        self.min_by_value_by(|a, b| a.cmp(b))
+    }
+
+    ///
+    /// same as `max_by_value`, comparing the values with `cmp`
+    ///
+    pub fn max_by_value_by<F: FnMut(&V, &V) -> Ordering>(&self, mut cmp: F) -> Option<(&K, &V)> {
+        self.iter_ref().fold(None, |best, (k, v)| match best {
+            Some((_, bv)) if cmp(v, bv) != Ordering::Greater => best,
+            _ => Some((k, v)),
+        })
+    }
+
+    ///
+    /// same as `min_by_value`, comparing the values with `cmp`
+    ///
+    pub fn min_by_value_by<F: FnMut(&V, &V) -> Ordering>(&self, mut cmp: F) -> Option<(&K, &V)> {
+        self.iter_ref().fold(None, |best, (k, v)| match best {
+            Some((_, bv)) if cmp(v, bv) != Ordering::Less => best,
+            _ => Some((k, v)),
+        })
+    }
+
+    ///
+    /// create and return a new map holding only the entries for which `f` returns true.
+    /// The entries are walked once in order and the tree is rebuilt balanced: O(n)
+    ///
+    pub fn retain<F: Fn(&K, &V) -> bool>(&self, f: F) -> Self {
+        Map::from_sorted_vec(
+            self.iter_ref()
+                .filter(|(k, v)| f(k, v))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        )
+    }
+
+    ///
+    /// create and return a new map holding the values returned by `f`, dropping the entries for which
+    /// it returns None. The keys keep their order, so the tree is rebuilt balanced in one pass: O(n)
+    ///
+    pub fn filter_map_values<W: Clone, F: FnMut(&K, &V) -> Option<W>>(
+        &self,
+        mut f: F,
+    ) -> Map<K, W> {
+        Map::from_sorted_vec(
+            self.iter_ref()
+                .filter_map(|(k, v)| f(k, v).map(|w| (k.clone(), w)))
+                .collect(),
+        )
+    }
+
+    ///
+    /// create and return a new map holding only the entries whose keys satisfy `f`, as `retain` does
+    ///
+    pub fn retain_keys<F: Fn(&K) -> bool>(&self, f: F) -> Self {
+        self.retain(|k, _| f(k))
+    }
+
+    ///
+    /// create and return the reverse index of the map: each value mapped to the set of keys holding it
+    ///
+    pub fn invert(&self) -> Map<V, Set<K>>
+    where
+        V: Ord,
+    {
+        let mut buckets = std::collections::BTreeMap::new();
+        for (k, v) in self.to_vec() {
+            buckets.entry(v).or_insert_with(Vec::new).push(k);
+        }
+        Map::from_sorted_vec(
+            buckets
+                .into_iter()
+                .map(|(v, keys)| (v, Set::from_sorted_vec(keys)))
+                .collect(),
+        )
+    }
+
+    ///
+    /// return true if every key of `self` exists in `other` with an equal value.
+    /// Walks both maps once, and stops at the first mismatch
+    ///
+    pub fn is_submap_of(&self, other: &Map<K, V>) -> bool
+    where
+        V: PartialEq,
+    {
+        let mut b = other.iter();
+        for (k, v) in self.iter() {
+            match b.find(|(k2, _)| *k2 >= k) {
+                Some((k2, v2)) if k2 == k && v2 == v => (),
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    ///
+    /// create and return a new map with every key mapped through `f`, which must be strictly
+    /// monotonic (`a < b` implies `f(a) < f(b)`). The tree shape is copied as is: O(n), no rebalancing
+    ///
+    pub fn map_keys_monotonic<K2: Ord + Clone, F: FnMut(&K) -> K2>(&self, mut f: F) -> Map<K2, V> {
+        Map {
+            n: S::map_keys_monotonic(&self.n, &mut f, &mut None),
+            size: self.size,
+        }
+    }
+
+    ///
+    /// returns an iterator over references to the key, value pairs, in ascending key order. Nothing
+    /// is cloned: the traversal stack (sized to the height of the tree) is the only allocation
+    ///
+    pub fn iter_ref(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        let mut stack = Vec::with_capacity(self.n.height());
+        push_left(&mut stack, self.n.as_ref());
+        std::iter::from_fn(move || match stack.pop()? {
+            Empty => None,
+            One(k, v) => Some((k, v)),
+            Node(_, _, k, v, r) => {
+                push_left(&mut stack, r.as_ref());
+                Some((k, v))
+            }
+        })
+    }
+
+    ///
+    /// create and return the set of the keys. The keys are already sorted, so the set is built
+    /// bottom-up without comparing them, and no value is cloned: O(n)
+    ///
+    pub fn keys_to_set(&self) -> Set<K> {
+        Set::from_sorted_vec(self.iter_ref().map(|(k, _)| k.clone()).collect())
+    }
+
+    ///
+    /// create and return a std `BTreeMap` holding the same entries
+    ///
+    pub fn to_std(&self) -> std::collections::BTreeMap<K, V> {
+        self.iter_ref()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    ///
+    /// create and return a hash map holding the same entries
+    ///
+    pub fn to_hashmap(&self) -> HashMap<K, V>
+    where
+        K: Hashable,
+    {
+        self.iter()
+            .fold(HashMap::empty(), |m, (k, v)| m.insert(k, v))
+    }
+
+    pub(crate) fn from_sorted_vec(v: Vec<(K, V)>) -> Self {
+        let size = v.len();
+        Self {
+            n: S::from_sorted_iter(size, &mut v.into_iter()),
+            size,
+        }
+    }
+}
+
+// push `t` and its left spine on the stack of an in-order traversal
+fn push_left<'a, K: Clone, V: Clone>(stack: &mut Vec<&'a MapNode<K, V>>, t: &'a MapNode<K, V>) {
+    let mut t = t;
+    loop {
+        match t {
+            Empty => return,
+            One(_, _) => {
+                stack.push(t);
+                return;
+            }
+            Node(_, l, _, _, _) => {
+                stack.push(t);
+                t = l;
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MapIter<'a, K: Ord + Clone, V: Clone> {
+    stack: Vec<N<K, V>>,
+    _phantom: PhantomData<&'a (K, V)>,
+}
+
+impl<'a, K: Ord + Clone, V: Clone> MapIter<'a, K, V> {
+    fn push_left(&mut self, n: &N<K, V>) {
+        let mut n = n.clone();
+        loop {
+            let next = match n.as_ref() {
+                Empty => return,
+                One(_, _) => None,
+                Node(_, l, _, _, _) => Some(l.clone()),
+            };
+            self.stack.push(n);
+            match next {
+                Some(l) => n = l,
+                None => return,
+            }
+        }
+    }
+
+    // along the search path to `start`, push only the nodes whose key is >= `start`
+    fn push_from(&mut self, n: &N<K, V>, start: &K) {
+        let mut n = n.clone();
+        loop {
+            let next = match n.as_ref() {
+                Empty => return,
+                One(k, _) => {
+                    if k >= start {
+                        self.stack.push(n);
+                    }
+                    return;
+                }
+                Node(_, l, k, _, _) if k >= start => {
+                    let l = l.clone();
+                    self.stack.push(n);
+                    l
+                }
+                Node(_, _, _, _, r) => r.clone(),
+            };
+            n = next;
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> std::iter::Iterator for MapIter<'a, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = self.stack.pop()?;
+        match n.as_ref() {
+            Empty => None,
+            One(k, v) => Some((k.clone(), v.clone())),
+            Node(_, _, k, v, r) => {
+                self.push_left(r);
+                Some((k.clone(), v.clone()))
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MapRevIter<'a, K: Ord + Clone, V: Clone> {
+    stack: Vec<N<K, V>>,
+    _phantom: PhantomData<&'a (K, V)>,
+}
+
+impl<'a, K: Ord + Clone, V: Clone> MapRevIter<'a, K, V> {
+    fn push_right(&mut self, n: &N<K, V>) {
+        let mut n = n.clone();
+        loop {
+            let next = match n.as_ref() {
+                Empty => return,
+                One(_, _) => None,
+                Node(_, _, _, _, r) => Some(r.clone()),
+            };
+            self.stack.push(n);
+            match next {
+                Some(r) => n = r,
+                None => return,
+            }
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> std::iter::Iterator for MapRevIter<'a, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = self.stack.pop()?;
+        match n.as_ref() {
+            Empty => None,
+            One(k, v) => Some((k.clone(), v.clone())),
+            Node(_, l, k, v, _) => {
+                self.push_right(l);
+                Some((k.clone(), v.clone()))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -329,6 +961,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn find() {
         let numbers = [5, 10, 3, 120, 4, 9, 27, 1, 45];
         let mut n = Map::empty();
@@ -364,6 +997,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn remove_one_from_one() {
         let mut n = Map::empty();
         n = n.insert(10, 10);
@@ -409,6 +1043,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
     fn remove_5000_from_10000_random() {
         let mut hs = std::collections::hash_set::HashSet::new();
         let mut numbers = Vec::new();
@@ -454,4 +1089,704 @@ mod tests {
         assert_eq!(n.find(numbers[0]).is_none(), true);
         assert_eq!(n.to_vec().len(), hs.len());
     }
+
+    #[test]
+    fn iter() {
+        let numbers = [5, 10, 3, 120, 4, 9, 27, 1, 45];
+        let sorted = [1, 3, 4, 5, 9, 10, 27, 45, 120];
+        let mut n = Map::empty();
+        for i in numbers {
+            n = n.insert(i, i * 2);
+        }
+
+        let v = n.iter().collect::<Vec<_>>();
+        assert_eq!(v.len(), sorted.len());
+        for i in 0..v.len() {
+            assert_eq!(v[i], (sorted[i], sorted[i] * 2));
+        }
+
+        assert_eq!(Map::<i32, i32>::empty().iter().next(), None);
+    }
+
+    #[test]
+    fn iter_from() {
+        let mut m = Map::empty();
+        for i in 0..1000 {
+            m = m.insert(i * 2, i);
+        }
+
+        // present key
+        assert!(m.iter_from(&500).eq((250..1000).map(|i| (i * 2, i))));
+        // absent key, between two present ones
+        assert!(m.iter_from(&501).eq((251..1000).map(|i| (i * 2, i))));
+        // below the minimum, above the maximum
+        assert!(m.iter_from(&-10).eq(m.iter()));
+        assert_eq!(m.iter_from(&1998).collect::<Vec<_>>(), vec![(1998, 999)]);
+        assert_eq!(m.iter_from(&1999).next(), None);
+
+        let mut n = Map::empty();
+        for _ in 0..10000 {
+            n = n.insert(rand(), rand());
+        }
+        for _ in 0..100 {
+            let start = rand();
+            assert!(n
+                .iter_from(&start)
+                .eq(n.iter().filter(|(k, _)| *k >= start)));
+        }
+
+        assert_eq!(Map::singleton(1, 2).iter_from(&1).count(), 1);
+        assert_eq!(Map::<i32, i32>::empty().iter_from(&0).next(), None);
+    }
+
+    #[test]
+    fn iter_rev() {
+        let mut hm = std::collections::HashMap::new();
+        for _ in 0..10000 {
+            let k = rand();
+            hm.insert(k, k / 2);
+        }
+
+        let mut n = Map::empty();
+        for (k, v) in hm.iter() {
+            n = n.insert(*k, *v);
+        }
+
+        let mut sorted = hm.into_iter().collect::<Vec<_>>();
+        sorted.sort();
+        sorted.reverse();
+        let v = n.iter_rev().collect::<Vec<_>>();
+        assert_eq!(v, sorted);
+        assert!(v.windows(2).all(|w| w[0].0 > w[1].0));
+
+        let mut fwd = n.iter().collect::<Vec<_>>();
+        fwd.reverse();
+        assert_eq!(fwd, v);
+
+        assert!(Map::singleton(1, 2).iter_rev().eq(std::iter::once((1, 2))));
+        assert_eq!(Map::<i32, i32>::empty().iter_rev().next(), None);
+    }
+
+    #[test]
+    fn join() {
+        let mut a = Map::empty();
+        for i in 0..100 {
+            a = a.insert(i, i * 10);
+        }
+
+        let mut b = Map::empty();
+        for i in (50..200).step_by(3) {
+            b = b.insert(i, format!("{}", i));
+        }
+
+        let j = a.join(&b);
+        let expected = (50..100).step_by(3).collect::<Vec<_>>();
+        assert_eq!(j.len(), expected.len());
+
+        let v = j.to_vec();
+        for i in 0..v.len() {
+            let k = expected[i];
+            assert_eq!(v[i], (k, (k * 10, format!("{}", k))));
+        }
+
+        assert_eq!(a.join(&Map::<i32, i32>::empty()).len(), 0);
+        assert!(a.join(&Map::<i32, i32>::empty()).is_empty());
+    }
+
+    #[test]
+    fn map_keys_monotonic() {
+        let mut n = Map::empty();
+        for i in 0..1000 {
+            let k = rand() % 100000;
+            if !n.exist(k) {
+                n = n.insert(k, i);
+            }
+        }
+
+        let m = n.map_keys_monotonic(|k| *k as i64 + 1000);
+        assert_eq!(m.len(), n.len());
+        assert_eq!(m.height(), n.height());
+
+        let v = n.to_vec();
+        let mv = m.to_vec();
+        for i in 0..v.len() {
+            assert_eq!(mv[i].0, v[i].0 as i64 + 1000);
+            assert_eq!(mv[i].1, v[i].1);
+        }
+        for i in 1..mv.len() {
+            assert!(mv[i - 1].0 < mv[i].0);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn map_keys_not_monotonic() {
+        let mut n = Map::empty();
+        for i in 0..10 {
+            n = n.insert(i, i);
+        }
+        n.map_keys_monotonic(|k| -k);
+    }
+
+    #[test]
+    fn is_submap_of() {
+        let mut full = Map::empty();
+        for i in 0..100 {
+            full = full.insert(i, i * 2);
+        }
+
+        let mut sub = Map::empty();
+        for i in (0..100).step_by(7) {
+            sub = sub.insert(i, i * 2);
+        }
+
+        assert!(sub.is_submap_of(&full));
+        assert!(full.is_submap_of(&full));
+        assert!(Map::empty().is_submap_of(&full));
+        assert!(!full.is_submap_of(&sub));
+
+        // the key is present, but the value differs
+        let diff = sub.remove(14).insert(14, 0);
+        assert!(!diff.is_submap_of(&full));
+
+        // the key is missing
+        let missing = sub.insert(1000, 2000);
+        assert!(!missing.is_submap_of(&full));
+        let missing = sub.insert(-1, -2);
+        assert!(!missing.is_submap_of(&full));
+    }
+
+    #[test]
+    fn invert() {
+        let mut n = Map::empty();
+        n = n.insert("a", 1);
+        n = n.insert("b", 2);
+        n = n.insert("c", 1);
+        n = n.insert("d", 3);
+        n = n.insert("e", 1);
+
+        let inv = n.invert();
+        assert_eq!(inv.len(), 3);
+        assert_eq!(inv.find(1).unwrap().to_vec(), vec!["a", "c", "e"]);
+        assert_eq!(inv.find(2).unwrap().to_vec(), vec!["b"]);
+        assert_eq!(inv.find(3).unwrap().to_vec(), vec!["d"]);
+        assert_eq!(inv.find(1).unwrap().len(), 3);
+        assert!(inv.find(4).is_none());
+
+        assert!(Map::<i32, i32>::empty().invert().is_empty());
+    }
+
+    #[test]
+    fn to_hashmap() {
+        let mut m = Map::empty();
+        for i in 0..10000usize {
+            m = m.insert(i * 7 % 10007, i);
+        }
+        let h = m.to_hashmap();
+        assert_eq!(h.len(), m.len());
+        for (k, v) in m.iter() {
+            assert_eq!(h.find(&k), Some(&v));
+        }
+
+        let back = h.to_ordered();
+        assert_eq!(back.len(), m.len());
+        assert_eq!(back.to_vec(), m.to_vec());
+        assert!(Map::<usize, usize>::empty().to_hashmap().is_empty());
+    }
+
+    #[test]
+    fn drop_1000000_sequential() {
+        // the tree stays balanced, so dropping it recursively only goes log(n) deep
+        let mut m = Map::empty();
+        for i in 0..1000000 {
+            m = m.insert(i, i);
+        }
+        assert!(m.height() <= 28);
+        drop(m);
+    }
+
+    #[test]
+    fn singleton() {
+        let m = Map::singleton(3, "three");
+        assert_eq!(m.len(), 1);
+        assert_eq!(m.height(), 1);
+        assert_eq!(m.find(3), Some(&"three"));
+        assert!(!m.exist(4));
+        assert_eq!(m.insert(4, "four").len(), 2);
+        assert!(m.remove(3).is_empty());
+    }
+
+    #[test]
+    fn iter_ref() {
+        let mut m = Map::empty();
+        for i in 0..100000 {
+            m = m.insert(i, format!("{}", i));
+        }
+
+        let mut iter = m.iter_ref();
+        let before = crate::tests::allocations();
+        let mut count = 0;
+        for (k, v) in &mut iter {
+            assert_eq!(*k, count);
+            assert_eq!(v.parse::<i32>().unwrap(), count);
+            count += 1;
+        }
+        assert_eq!(crate::tests::allocations(), before);
+        assert_eq!(count, 100000);
+        assert!(m.iter_ref().map(|(k, v)| (*k, v.clone())).eq(m.iter()));
+
+        assert!(Map::singleton(1, 2)
+            .iter_ref()
+            .eq(std::iter::once((&1, &2))));
+        assert_eq!(Map::<i32, i32>::empty().iter_ref().count(), 0);
+    }
+
+    #[test]
+    fn entry_or() {
+        let mut m = Map::empty();
+        for i in 0..1000 {
+            m = m.insert(i * 2, i);
+        }
+
+        // present keys: the map is unchanged, and the default isn't evaluated
+        for i in 0..1000 {
+            let (v, m2) = m.entry_or(i * 2, || panic!("not expected"));
+            assert_eq!(v, i);
+            assert_eq!(m2.len(), 1000);
+            assert!(Arc::ptr_eq(&m2.n, &m.n));
+        }
+
+        // absent keys: the default is inserted
+        let mut m2 = m.clone();
+        for i in 0..1000 {
+            let (v, m3) = m2.entry_or(i * 2 + 1, || -i);
+            assert_eq!(v, -i);
+            assert_eq!(m3.len(), m2.len() + 1);
+            assert_eq!(m3.find(i * 2 + 1), Some(&-i));
+            m2 = m3;
+        }
+        assert_eq!(m2.len(), 2000);
+        assert!(m2.height() <= 12);
+        let v = m2.to_vec();
+        assert_eq!(v.len(), 2000);
+        for (i, (k, _)) in v.iter().enumerate() {
+            assert_eq!(*k, i as i32);
+        }
+
+        let (v, m) = Map::empty().entry_or(1, || 10);
+        assert_eq!(v, 10);
+        assert_eq!(m.to_vec(), vec![(1, 10)]);
+    }
+
+    #[test]
+    fn retain() {
+        let mut m = Map::empty();
+        let mut reference = std::collections::BTreeMap::new();
+        while reference.len() < 10000 {
+            let (k, v) = (rand(), rand());
+            if !m.exist(k) {
+                m = m.insert(k, v);
+                reference.insert(k, v);
+            }
+        }
+
+        let even = m.retain(|k, _| k % 2 == 0);
+        let expected = reference
+            .iter()
+            .filter(|(k, _)| *k % 2 == 0)
+            .map(|(k, v)| (*k, *v))
+            .collect::<Vec<_>>();
+        assert_eq!(even.len(), expected.len());
+        assert_eq!(even.to_vec(), expected);
+
+        let threshold = 100;
+        let big = m.retain(|_, v| *v > threshold);
+        let expected = reference
+            .iter()
+            .filter(|(_, v)| **v > threshold)
+            .map(|(k, v)| (*k, *v))
+            .collect::<Vec<_>>();
+        assert_eq!(big.len(), expected.len());
+        assert_eq!(big.to_vec(), expected);
+        for (k, v) in expected.iter() {
+            assert_eq!(big.find(*k), Some(v));
+        }
+
+        assert_eq!(m.retain(|_, _| true).len(), 10000);
+        assert!(m.retain(|_, _| false).is_empty());
+
+        let odd = m.retain_keys(|k| k % 2 != 0);
+        assert_eq!(odd.len(), m.len() - even.len());
+        assert!(odd
+            .iter_ref()
+            .all(|(k, v)| k % 2 != 0 && reference[k] == *v));
+    }
+
+    #[test]
+    fn clone_default() {
+        let m: Map<i32, i32> = Default::default();
+        assert!(m.is_empty());
+
+        let mut m = m;
+        for i in 0..1000 {
+            m = m.insert(i, i);
+        }
+        let c = m.clone();
+        assert!(Arc::ptr_eq(&c.n, &m.n));
+        assert_eq!(c.len(), m.len());
+
+        let c = c.insert(1000, 1000).remove(0);
+        assert_eq!(m.len(), 1000);
+        assert_eq!(m.find(0), Some(&0));
+        assert!(!m.exist(1000));
+        assert_eq!(c.len(), 1000);
+        assert!(!c.exist(0));
+
+        #[derive(Clone, Default)]
+        struct Holder {
+            m: Map<i32, i32>,
+        }
+        let h = Holder::default();
+        assert!(h.clone().m.is_empty());
+    }
+
+    #[test]
+    fn std_conversions() {
+        let mut std_map = std::collections::BTreeMap::new();
+        for _ in 0..10000 {
+            std_map.insert(rand(), rand());
+        }
+
+        let m = Map::from(std_map.clone());
+        assert_eq!(m.len(), std_map.len());
+        assert!(m.iter_ref().eq(std_map.iter()));
+
+        let back = m.to_std();
+        assert_eq!(back, std_map);
+        assert!(back.iter().eq(m.iter_ref()));
+
+        assert!(Map::<i32, i32>::from(std::collections::BTreeMap::new()).is_empty());
+        assert!(Map::<i32, i32>::empty().to_std().is_empty());
+    }
+
+    #[test]
+    fn fold_values() {
+        let mut m = Map::empty();
+        let mut expected = 0u64;
+        for i in 0..1000 {
+            m = m.insert(i, (i * 3) as u64);
+            expected += (i * 3) as u64;
+        }
+
+        assert_eq!(m.sum_values(), expected);
+        assert_eq!(m.fold_values(0, |acc, v| acc + v), expected);
+        assert_eq!(m.fold_values(0, |acc, v| u64::max(acc, *v)), 2997);
+        let order = m.fold_values(Vec::new(), |mut acc, v| {
+            acc.push(*v);
+            acc
+        });
+        assert_eq!(
+            order,
+            m.to_vec().into_iter().map(|(_, v)| v).collect::<Vec<_>>()
+        );
+
+        assert_eq!(Map::<i32, u64>::empty().sum_values(), 0);
+    }
+
+    #[test]
+    fn max_min_by_value() {
+        let mut m = Map::empty();
+        let mut reference = std::collections::BTreeMap::new();
+        for _ in 0..10000 {
+            let (k, v) = (rand(), rand() % 1000);
+            m = m.insert(k, v);
+            reference.insert(k, v);
+        }
+
+        let max = *reference.values().max().unwrap();
+        let min = *reference.values().min().unwrap();
+        // ties: the smallest key wins
+        let first_max = reference.iter().find(|(_, v)| **v == max).unwrap();
+        let first_min = reference.iter().find(|(_, v)| **v == min).unwrap();
+        assert_eq!(m.max_by_value(), Some(first_max));
+        assert_eq!(m.min_by_value(), Some(first_min));
+
+        // reversed comparator
+        assert_eq!(m.max_by_value_by(|a, b| b.cmp(a)), Some(first_min));
+        assert_eq!(m.min_by_value_by(|a, b| b.cmp(a)), Some(first_max));
+
+        let ties = Map::empty().insert(3, 5).insert(1, 5).insert(2, 5);
+        assert_eq!(ties.max_by_value(), Some((&1, &5)));
+        assert_eq!(ties.min_by_value(), Some((&1, &5)));
+
+        let e = Map::<i32, i32>::empty();
+        assert_eq!(e.max_by_value(), None);
+        assert_eq!(e.min_by_value(), None);
+    }
+
+    #[test]
+    fn node_count() {
+        // the distinct nodes reachable from the given trees
+        fn distinct(t: &N<i32, i32>, seen: &mut std::collections::HashSet<*const S<i32, i32>>) {
+            if matches!(t.as_ref(), Empty) || !seen.insert(Arc::as_ptr(t)) {
+                return;
+            }
+            if let Node(_, l, _, _, r) = t.as_ref() {
+                distinct(l, seen);
+                distinct(r, seen);
+            }
+        }
+
+        let mut m = Map::empty();
+        for i in 0..100000 {
+            m = m.insert(i, i);
+        }
+        assert_eq!(m.node_count(), 100000);
+
+        let m2 = m.clone().insert(-1, -1);
+        assert_eq!(m2.node_count(), 100001);
+
+        // only the nodes on the path to the new key (and the rotated ones) are new
+        let mut seen = std::collections::HashSet::new();
+        distinct(&m.n, &mut seen);
+        assert_eq!(seen.len(), m.node_count());
+        distinct(&m2.n, &mut seen);
+        let new_nodes = seen.len() - m.node_count();
+        assert!(new_nodes >= 1 && new_nodes <= 2 * m.height());
+
+        assert_eq!(m2.remove(-1).node_count(), 100000);
+        assert_eq!(Map::singleton(1, 1).node_count(), 1);
+        assert_eq!(Map::<i32, i32>::empty().node_count(), 0);
+    }
+
+    #[test]
+    fn join_with_pivot() {
+        // split `t` around `k`: the keys < k, the value of k, the keys > k
+        fn split(t: &N<i32, i32>, k: i32) -> (N<i32, i32>, Option<i32>, N<i32, i32>) {
+            match t.as_ref() {
+                Empty => (S::empty(), None, S::empty()),
+                One(k2, v2) if k == *k2 => (S::empty(), Some(*v2), S::empty()),
+                Node(_, l, k2, v2, r) if k == *k2 => (l.clone(), Some(*v2), r.clone()),
+                One(k2, v2) if k < *k2 => (S::empty(), None, t.clone()),
+                One(_, _) => (t.clone(), None, S::empty()),
+                Node(_, l, k2, v2, r) if k < *k2 => {
+                    let (ll, v, lr) = split(l, k);
+                    (ll, v, S::join(&lr, *k2, *v2, r))
+                }
+                Node(_, l, k2, v2, r) => {
+                    let (rl, v, rr) = split(r, k);
+                    (S::join(l, *k2, *v2, &rl), v, rr)
+                }
+            }
+        }
+
+        let mut m = Map::empty();
+        for _ in 0..10000 {
+            m = m.insert(rand(), rand());
+        }
+        let v = m.to_vec();
+
+        for i in [0, 1, v.len() / 3, v.len() / 2, v.len() - 1] {
+            let (pk, pv) = v[i];
+            let (l, found, r) = split(&m.n, pk);
+            assert_eq!(found, Some(pv));
+            let left = Map { n: l, size: i };
+            let right = Map {
+                n: r,
+                size: v.len() - i - 1,
+            };
+            assert_eq!(left.to_vec(), v[..i].to_vec());
+            assert_eq!(right.to_vec(), v[i + 1..].to_vec());
+
+            let joined = Map::join_with_pivot(&left, (pk, pv), &right);
+            assert_eq!(joined.len(), m.len());
+            assert_eq!(joined.to_vec(), v);
+            // still balanced
+            assert!(joined.height() <= m.height() + 1);
+            assert_eq!(joined.remove(pk).len(), m.len() - 1);
+        }
+
+        // very different heights
+        let small = Map::singleton(-1, -1);
+        let big = Map::from_sorted_vec((1..100000).map(|i| (i, i)).collect());
+        let joined = Map::join_with_pivot(&small, (0, 0), &big);
+        assert_eq!(joined.len(), 100001);
+        assert!(joined.iter().eq((-1..100000).map(|i| (i, i))));
+        assert!(joined.height() <= big.height() + 1);
+        let joined = Map::join_with_pivot(&Map::empty(), (0, 0), &Map::empty());
+        assert_eq!(joined.to_vec(), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn filter_map_values() {
+        let mut m = Map::empty();
+        let mut reference = std::collections::BTreeMap::new();
+        for _ in 0..10000 {
+            let (k, v) = (rand(), rand() - (1 << 6));
+            if !m.exist(k) {
+                m = m.insert(k, v);
+                reference.insert(k, v);
+            }
+        }
+
+        let strings = m.filter_map_values(|_, v| match *v >= 0 {
+            true => Some(format!("{}", v)),
+            false => None,
+        });
+        let expected = reference
+            .iter()
+            .filter(|(_, v)| **v >= 0)
+            .map(|(k, v)| (*k, format!("{}", v)))
+            .collect::<Vec<_>>();
+        assert!(expected.len() < m.len());
+        assert_eq!(strings.len(), expected.len());
+        assert_eq!(strings.to_vec(), expected);
+        for (k, v) in expected.iter() {
+            assert_eq!(strings.find(*k), Some(v));
+        }
+
+        assert_eq!(m.filter_map_values(|k, _| Some(*k)).len(), m.len());
+        assert!(m.filter_map_values(|_, _| None::<i32>).is_empty());
+    }
+
+    #[test]
+    fn contains_key_value() {
+        let m = Map::empty()
+            .insert(1, "one")
+            .insert(2, "two")
+            .insert(3, "odd")
+            .insert(5, "odd")
+            .insert(8, "even");
+
+        for k in [1, 2, 3, 5, 8] {
+            assert!(m.contains_key(&k));
+        }
+        for k in [0, 4, 6, 7, 9, -1] {
+            assert!(!m.contains_key(&k));
+        }
+
+        assert!(m.contains_value(&"one"));
+        assert!(m.contains_value(&"odd"));
+        assert!(m.contains_value(&"even"));
+        assert!(!m.contains_value(&"three"));
+        assert!(!m.remove(3).remove(5).contains_value(&"odd"));
+        assert!(m.remove(3).contains_value(&"odd"));
+
+        let e = Map::<i32, i32>::empty();
+        assert!(!e.contains_key(&0));
+        assert!(!e.contains_value(&0));
+    }
+
+    #[test]
+    fn keys_to_set() {
+        let mut m = Map::empty();
+        for i in 0..10000 {
+            m = m.insert(rand(), format!("{}", i));
+        }
+
+        let s = m.keys_to_set();
+        assert_eq!(s.len(), m.len());
+        assert!(s.iter_ref().eq(m.iter_ref().map(|(k, _)| k)));
+        // balanced: log2(10000) ~ 13.3
+        assert!(s.height() <= 15);
+        assert!(s.height() <= m.height());
+
+        assert_eq!(Map::singleton(1, 2).keys_to_set().to_vec(), vec![1]);
+        assert!(Map::<i32, i32>::empty().keys_to_set().is_empty());
+    }
+
+    #[test]
+    fn upsert_increment() {
+        let text = "the quick brown fox jumps over the lazy dog the fox";
+        let mut m: Map<String, u32> = Map::empty();
+        for w in text.split(' ') {
+            m = m.increment(w.to_string(), 1);
+        }
+        assert_eq!(m.len(), 8);
+        assert_eq!(m.find("the".to_string()), Some(&3));
+        assert_eq!(m.find("fox".to_string()), Some(&2));
+        assert_eq!(m.find("dog".to_string()), Some(&1));
+        assert_eq!(m.find("cat".to_string()), None);
+        assert_eq!(m.fold_values(0, |acc, v| acc + v), 11);
+
+        let mut m = Map::empty();
+        let mut reference = std::collections::BTreeMap::new();
+        for _ in 0..10000 {
+            let k = rand() % 1000;
+            m = m.upsert(k, 1, |v| v * 2);
+            reference.entry(k).and_modify(|v| *v *= 2).or_insert(1);
+        }
+        assert_eq!(m.len(), reference.len());
+        assert!(m
+            .iter_ref()
+            .map(|(k, v)| (*k, *v))
+            .eq(reference.into_iter()));
+        assert!(m.height() <= 20);
+
+        assert_eq!(Map::empty().upsert(1, 5, |v| v + 1).to_vec(), vec![(1, 5)]);
+        assert_eq!(
+            Map::singleton(1, 5).upsert(1, 0, |v| v + 1).to_vec(),
+            vec![(1, 6)]
+        );
+    }
+
+    #[test]
+    fn remove_all() {
+        let mut reference = std::collections::BTreeMap::new();
+        let mut m = Map::empty();
+        while reference.len() < 10000 {
+            let (k, v) = (rand(), rand());
+            if !m.exist(k) {
+                m = m.insert(k, v);
+                reference.insert(k, v);
+            }
+        }
+
+        let mut batch = reference.keys().cloned().step_by(2).collect::<Vec<_>>();
+        batch.push(batch[0]);
+        batch.extend(
+            (0..10)
+                .map(|_| rand())
+                .filter(|k| !reference.contains_key(k)),
+        );
+        for k in batch.iter() {
+            reference.remove(k);
+        }
+
+        let r = m.remove_all(batch);
+        assert_eq!(r.len(), 5000);
+        assert!(r.iter().eq(reference.into_iter()));
+        assert_eq!(m.len(), 10000);
+        assert!(m.remove_all(m.iter().map(|(k, _)| k)).is_empty());
+    }
+
+    #[test]
+    fn zip_values() {
+        let mut names = Map::empty();
+        let mut ages = Map::empty();
+        for i in 0..1000 {
+            names = names.insert(i * 2, format!("name{}", i * 2));
+            ages = ages.insert(i * 3, (i * 3) as u32);
+        }
+
+        let z = names.zip_values(&ages, |n, a| format!("{}:{}", n, a));
+        // the multiples of 6 below 2000
+        let expected = (0..334)
+            .map(|i| (i * 6, format!("name{}:{}", i * 6, i * 6)))
+            .collect::<Vec<_>>();
+        assert_eq!(z.len(), expected.len());
+        assert_eq!(z.to_vec(), expected);
+
+        assert_eq!(
+            ages.zip_values(&names, |a, n| n.len() as u32 + a).len(),
+            334
+        );
+        assert!(names
+            .zip_values(&Map::<i32, u32>::empty(), |_, _| 0)
+            .is_empty());
+        assert!(Map::<i32, u32>::empty()
+            .zip_values(&ages, |_, _| 0)
+            .is_empty());
+    }
 }
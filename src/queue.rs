@@ -27,77 +27,281 @@
 // ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE
 // POSSIBILITY OF SUCH DAMAGE.
 //
-use std::sync::Arc;
+use crate::Arc;
+use std::sync::{Mutex, OnceLock};
 
 use crate::list::*;
 
-enum QueueNode<E: Clone> {
-    Empty,
-    Node { back: L<E>, front: L<E> },
+//
+// Real-time queue (Okasaki): the front is a lazy stream built by incrementally rotating the back
+// list onto it, and the schedule forces one of its cells per operation. Since the rotation is
+// started as soon as the back gets longer than the front, every cell of the front has already
+// been forced by the time it is reached, which makes enqueue/dequeue worst-case O(1).
+//
+
+enum Cell<E: Clone> {
+    Nil,
+    Cons(E, S<E>),
+}
+
+use Cell::*;
+
+// pending rotation: front ++ reverse(back) ++ acc, with |back| = |front| + 1
+struct Rotation<E: Clone> {
+    front: S<E>,
+    back: L<E>,
+    acc: S<E>,
 }
 
-use QueueNode::*;
+struct StreamNode<E: Clone> {
+    cell: OnceLock<Cell<E>>,
+    rotation: Mutex<Option<Rotation<E>>>,
+}
 
+struct QueueNode<E: Clone> {
+    front: S<E>,
+    front_len: usize,
+    back: L<E>,
+    schedule: S<E>,
+}
+
+type S<E> = Arc<StreamNode<E>>;
 type N<E> = Arc<QueueNode<E>>;
 type L<E> = List<E>;
 
+fn evaluated<E: Clone>(c: Cell<E>) -> S<E> {
+    Arc::new(StreamNode {
+        cell: OnceLock::from(c),
+        rotation: Mutex::new(None),
+    })
+}
+
+fn nil<E: Clone>() -> S<E> {
+    evaluated(Nil)
+}
+
+fn cons<E: Clone>(e: E, s: &S<E>) -> S<E> {
+    evaluated(Cons(e, s.clone()))
+}
+
+fn suspend<E: Clone>(r: Rotation<E>) -> S<E> {
+    Arc::new(StreamNode {
+        cell: OnceLock::new(),
+        rotation: Mutex::new(Some(r)),
+    })
+}
+
+fn rotate<E: Clone>(r: Rotation<E>) -> Cell<E> {
+    let y = r.back.top().clone();
+    match force(&r.front) {
+        Nil => Cons(y, r.acc),
+        Cons(x, f) => Cons(
+            x.clone(),
+            suspend(Rotation {
+                front: f.clone(),
+                back: r.back.pop(),
+                acc: cons(y, &r.acc),
+            }),
+        ),
+    }
+}
+
+fn force<E: Clone>(s: &S<E>) -> &Cell<E> {
+    s.cell.get_or_init(|| {
+        let r = s.rotation.lock().unwrap().take();
+        rotate(r.expect("stream cell is neither evaluated nor suspended"))
+    })
+}
+
+// move the children out of a stream node, so that they can be dropped without recursing
+fn drop_children<E: Clone>(n: &mut StreamNode<E>, pending: &mut Vec<S<E>>) {
+    if let Some(Cons(_, next)) = n.cell.take() {
+        pending.push(next);
+    }
+    if let Ok(r) = n.rotation.get_mut() {
+        if let Some(r) = r.take() {
+            pending.push(r.front);
+            pending.push(r.acc);
+        }
+    }
+}
+
+impl<E: Clone> Drop for StreamNode<E> {
+    fn drop(&mut self) {
+        let mut pending = Vec::new();
+        drop_children(self, &mut pending);
+        while let Some(mut s) = pending.pop() {
+            if let Some(n) = Arc::get_mut(&mut s) {
+                drop_children(n, &mut pending);
+            }
+        }
+    }
+}
+
+// iterate a stream by reference, forcing its cells on the way
+fn stream_iter<E: Clone>(s: &S<E>) -> impl Iterator<Item = &E> {
+    let mut s = s;
+    std::iter::from_fn(move || match force(s) {
+        Nil => None,
+        Cons(e, next) => {
+            s = next;
+            Some(e)
+        }
+    })
+}
+
 fn empty<E: Clone>() -> N<E> {
-    Arc::new(Empty)
+    let n = nil();
+    Arc::new(QueueNode {
+        front: n.clone(),
+        front_len: 0,
+        back: L::empty(),
+        schedule: n,
+    })
+}
+
+// build a queue out of its front elements (oldest first) and its back list. The front is fully
+// evaluated, and the schedule points to the cells that are not yet matched by the back list
+fn from_parts<E: Clone>(mut front: Vec<E>, back: L<E>) -> N<E> {
+    let back = if back.len() > front.len() {
+        front.extend(back.to_vec().into_iter().rev());
+        L::empty()
+    } else {
+        back
+    };
+
+    let front_len = front.len();
+    let mut f = nil();
+    let mut schedule = f.clone();
+    for (i, e) in front.into_iter().enumerate().rev() {
+        f = cons(e, &f);
+        if i == back.len() {
+            schedule = f.clone();
+        }
+    }
+
+    Arc::new(QueueNode {
+        front: f,
+        front_len,
+        back,
+        schedule,
+    })
 }
-fn node<E: Clone>(back: L<E>, front: L<E>) -> N<E> {
-    match (back.len(), front.len()) {
-        (0, 0) => empty(),
-        _ => Arc::new(Node { back, front }),
+
+// force one cell of the schedule, or start a new rotation once the schedule is exhausted
+fn step<E: Clone>(front: &mut S<E>, front_len: &mut usize, back: &mut L<E>, schedule: &mut S<E>) {
+    let next = match force(schedule) {
+        Cons(_, s) => Some(s.clone()),
+        Nil => None,
+    };
+    match next {
+        Some(s) => *schedule = s,
+        None => {
+            *front_len += back.len();
+            *front = suspend(Rotation {
+                front: front.clone(),
+                back: std::mem::replace(back, L::empty()),
+                acc: nil(),
+            });
+            *schedule = front.clone();
+        }
     }
 }
 
+fn exec<E: Clone>(mut front: S<E>, mut front_len: usize, mut back: L<E>, schedule: &S<E>) -> N<E> {
+    let mut schedule = schedule.clone();
+    step(&mut front, &mut front_len, &mut back, &mut schedule);
+    Arc::new(QueueNode {
+        front,
+        front_len,
+        back,
+        schedule,
+    })
+}
+
 fn enqueue<E: Clone>(q: &N<E>, e: E) -> N<E> {
-    match q.as_ref() {
-        Empty => node(L::empty().push(e), L::empty()),
-        Node { back: b, front: f } => node(b.push(e), f.clone()),
+    exec(q.front.clone(), q.front_len, q.back.push(e), &q.schedule)
+}
+
+// push the elements on the back list, stepping the schedule once per element (as `enqueue` does),
+// but building a single queue node at the end
+fn enqueue_all<E: Clone, I: Iterator<Item = E>>(q: &N<E>, iter: I) -> N<E> {
+    let mut front = q.front.clone();
+    let mut front_len = q.front_len;
+    let mut back = q.back.clone();
+    let mut schedule = q.schedule.clone();
+    for e in iter {
+        back = back.push(e);
+        step(&mut front, &mut front_len, &mut back, &mut schedule);
     }
+    Arc::new(QueueNode {
+        front,
+        front_len,
+        back,
+        schedule,
+    })
 }
 
 fn dequeue<E: Clone>(q: &N<E>) -> (E, N<E>) {
-    match q.as_ref() {
-        Empty => panic!("queue is empty"),
-        Node { back: b, front: f } => match (b.len(), f.len()) {
-            (0, 0) => panic!("queue is empty"),
-            (_, 0) => {
-                let l = b.rev();
-                let p = l.pop();
-                (l.top().clone(), node(L::empty(), p))
-            }
-            (_, _) => {
-                let p = f.pop();
-                (f.top().clone(), node(b.clone(), p))
-            }
-        },
+    match force(&q.front) {
+        Nil => panic!("queue is empty"),
+        Cons(e, f) => (
+            e.clone(),
+            exec(f.clone(), q.front_len - 1, q.back.clone(), &q.schedule),
+        ),
     }
 }
 
-fn len<E: Clone>(q: &N<E>) -> usize {
-    match q.as_ref() {
-        Empty => 0,
-        Node { back: b, front: f } => b.len() + f.len(),
-    }
-}
-
-fn to_vec<E: Clone>(l: &N<E>) -> Vec<E> {
-    let mut v = Vec::new();
-    let mut n = l.clone();
-    loop {
-        match n.as_ref() {
-            Empty => return v,
-            _ => {
-                let (e, nn) = dequeue(&n);
-                v.push(e.clone());
-                n = nn;
+// dequeue `n` elements (at most), stepping the schedule once per element (as `dequeue` does),
+// but building a single queue node at the end
+fn dequeue_n<E: Clone>(q: &N<E>, n: usize) -> (Vec<E>, N<E>) {
+    let mut front = q.front.clone();
+    let mut front_len = q.front_len;
+    let mut back = q.back.clone();
+    let mut schedule = q.schedule.clone();
+    let mut res = Vec::with_capacity(usize::min(n, len(q)));
+    while res.len() < n {
+        let next = match force(&front) {
+            Nil => break,
+            Cons(e, f) => {
+                res.push(e.clone());
+                f.clone()
             }
-        }
+        };
+        front = next;
+        front_len -= 1;
+        step(&mut front, &mut front_len, &mut back, &mut schedule);
+    }
+    (
+        res,
+        Arc::new(QueueNode {
+            front,
+            front_len,
+            back,
+            schedule,
+        }),
+    )
+}
+
+fn front<E: Clone>(q: &N<E>) -> Option<&E> {
+    match force(&q.front) {
+        Nil => None,
+        Cons(e, _) => Some(e),
+    }
+}
+
+// the most recently enqueued element: the top of the back list, or the last cell of the front
+fn back<E: Clone>(q: &N<E>) -> Option<&E> {
+    match q.back.is_empty() {
+        false => Some(q.back.top()),
+        true => stream_iter(&q.front).last(),
     }
 }
 
+fn len<E: Clone>(q: &N<E>) -> usize {
+    q.front_len + q.back.len()
+}
+
 #[derive(Clone)]
 pub struct Queue<E: Clone> {
     n: N<E>,
@@ -111,6 +315,15 @@ impl<E: Clone> Queue<E> {
         Self { n: empty() }
     }
 
+    ///
+    /// create and return a new queue holding only the given element
+    ///
+    pub fn singleton(e: E) -> Self {
+        Self {
+            n: from_parts(vec![e], List::empty()),
+        }
+    }
+
     ///
     /// create and return a new queue with the new element at the end
     ///
@@ -128,6 +341,170 @@ impl<E: Clone> Queue<E> {
         (e, Self { n })
     }
 
+    ///
+    /// create a new queue with the oldest element removed and returned, or None if the queue is empty
+    ///
+    pub fn try_dequeue(&self) -> Option<(E, Self)> {
+        match self.is_empty() {
+            true => None,
+            false => Some(self.dequeue()),
+        }
+    }
+
+    ///
+    /// dequeue the `n` oldest elements at once (`n` being clamped to the length of the queue): return
+    /// them in FIFO order along with the remaining queue. A single queue is built, rather than one per element
+    ///
+    pub fn split_first_n(&self, n: usize) -> (Vec<E>, Self) {
+        let (v, n) = dequeue_n(&self.n, n);
+        (v, Self { n })
+    }
+
+    ///
+    /// return a reference to the oldest element (the next one to be dequeued), or None if the queue is empty
+    ///
+    pub fn front(&self) -> Option<&E> {
+        front(&self.n)
+    }
+
+    ///
+    /// return a reference to the newest element (the most recently enqueued), or None if the queue is empty.
+    /// O(1) when elements were enqueued since the last rotation, O(n) otherwise (the front is walked)
+    ///
+    pub fn back(&self) -> Option<&E> {
+        back(&self.n)
+    }
+
+    ///
+    /// create and return a new queue holding the elements of `self` followed by the elements of `other`.
+    /// The elements of `other` are enqueued in order: O(len(other))
+    ///
+    pub fn append(&self, other: &Queue<E>) -> Self {
+        self.enqueue_all(other.iter_ref().cloned())
+    }
+
+    ///
+    /// create and return a new queue with the elements of `iter` enqueued in order. A single queue is
+    /// built at the end, rather than one per element
+    ///
+    pub fn enqueue_all<I: IntoIterator<Item = E>>(&self, iter: I) -> Self {
+        Self {
+            n: enqueue_all(&self.n, iter.into_iter()),
+        }
+    }
+
+    ///
+    /// create and return a new queue with `f` applied to every element (in FIFO order)
+    ///
+    pub fn map<W: Clone, F: FnMut(&E) -> W>(&self, mut f: F) -> Queue<W> {
+        let front = stream_iter(&self.n.front).map(&mut f).collect::<Vec<_>>();
+        let mut back = self.n.back.iter_ref().collect::<Vec<_>>();
+        back.reverse();
+        let mut back = back.into_iter().map(&mut f).collect::<Vec<_>>();
+        back.reverse();
+        Queue {
+            n: from_parts(front, List::from_vec(back)),
+        }
+    }
+
+    ///
+    /// create and return a new queue holding only the elements for which `f` returns true (in FIFO order)
+    ///
+    pub fn filter<F: Fn(&E) -> bool>(&self, f: F) -> Self {
+        let front = stream_iter(&self.n.front)
+            .filter(|e| f(e))
+            .cloned()
+            .collect::<Vec<_>>();
+        let back = self
+            .n
+            .back
+            .iter_ref()
+            .filter(|e| f(e))
+            .cloned()
+            .collect::<Vec<_>>();
+        Self {
+            n: from_parts(front, List::from_vec(back)),
+        }
+    }
+
+    ///
+    /// create and return a new queue with the elements in reverse order: the newest element is dequeued first.
+    /// The back list becomes the front, and the front becomes the back: O(n)
+    ///
+    pub fn rev(&self) -> Self {
+        let front = self.n.back.iter_ref().cloned().collect::<Vec<_>>();
+        let back = List::from_vec(stream_iter(&self.n.front).cloned().collect());
+        Self {
+            n: from_parts(front, back),
+        }
+    }
+
+    ///
+    /// split the queue in two: the first queue holds the `i` oldest elements, the second one the rest
+    /// (both in FIFO order). `i` is clamped to the length of the queue: O(n)
+    ///
+    pub fn split_at(&self, i: usize) -> (Self, Self) {
+        let mut front = self.iter_ref().cloned().collect::<Vec<_>>();
+        let back = front.split_off(i.min(front.len()));
+        (
+            Self {
+                n: from_parts(front, List::empty()),
+            },
+            Self {
+                n: from_parts(back, List::empty()),
+            },
+        )
+    }
+
+    ///
+    /// consume the queue and return a new one with the elements of `iter` enqueued in order
+    ///
+    pub fn extended<I: IntoIterator<Item = E>>(self, iter: I) -> Self {
+        self.enqueue_all(iter)
+    }
+
+    ///
+    /// fold the elements in FIFO order, without dequeuing them. The front is walked in place, then
+    /// the back list (newest first) is walked bottom-up without being collected
+    ///
+    pub fn fold<B, F: FnMut(B, &E) -> B>(&self, init: B, mut f: F) -> B {
+        let acc = stream_iter(&self.n.front).fold(init, &mut f);
+        self.n.back.fold_rev_ref(acc, f)
+    }
+
+    ///
+    /// reduce the elements in FIFO order with `f`, None if the queue is empty. The oldest element
+    /// is cloned as the initial value, the others are passed by reference
+    ///
+    pub fn reduce<F: FnMut(E, &E) -> E>(&self, mut f: F) -> Option<E> {
+        // the front is only empty when the whole queue is
+        let mut front = stream_iter(&self.n.front);
+        let first = front.next()?.clone();
+        let acc = front.fold(first, &mut f);
+        Some(self.n.back.fold_rev_ref(acc, f))
+    }
+
+    ///
+    /// returns a reference to the oldest element for which `f` returns true, None if there is none.
+    /// The front is scanned first, then the back list (keeping its last match, the oldest one):
+    /// nothing is reversed nor allocated
+    ///
+    pub fn find<F: Fn(&E) -> bool>(&self, f: F) -> Option<&E> {
+        stream_iter(&self.n.front)
+            .find(|e| f(e))
+            .or_else(|| self.n.back.iter_ref().filter(|e| f(e)).last())
+    }
+
+    ///
+    /// return true if the queue holds an element equal to `e`
+    ///
+    pub fn contains(&self, e: &E) -> bool
+    where
+        E: PartialEq,
+    {
+        stream_iter(&self.n.front).any(|x| x == e) || self.n.back.iter_ref().any(|x| x == e)
+    }
+
     ///
     /// return true if the queue is empty
     ///
@@ -146,10 +523,86 @@ impl<E: Clone> Queue<E> {
     /// walk the queue and build a vector and return it (oldest elements first)
     ///
     pub fn to_vec(&self) -> Vec<E> {
-        to_vec(&self.n)
+        self.iter().collect()
+    }
+
+    ///
+    /// returns an iterator (oldest elements first)
+    ///
+    pub fn iter(&self) -> QueueIter<E> {
+        QueueIter {
+            queue: self.clone(),
+        }
+    }
+
+    ///
+    /// returns an iterator over references to the elements (oldest elements first). Unlike `iter`,
+    /// it doesn't clone the elements nor dequeue them, but references to the back list (up to
+    /// `len() / 2` of them) are collected up front to walk it oldest first
+    ///
+    pub fn iter_ref(&self) -> impl Iterator<Item = &E> + '_ {
+        let mut back = self.n.back.iter_ref().collect::<Vec<_>>();
+        back.reverse();
+        stream_iter(&self.n.front).chain(back)
+    }
+}
+
+#[derive(Clone)]
+pub struct QueueIter<E: Clone> {
+    queue: Queue<E>,
+}
+
+impl<E: Clone> std::iter::Iterator for QueueIter<E> {
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (e, q) = self.queue.try_dequeue()?;
+        self.queue = q;
+        Some(e)
+    }
+}
+
+impl<E: Clone> std::iter::FromIterator<E> for Queue<E> {
+    fn from_iter<I: IntoIterator<Item = E>>(iter: I) -> Self {
+        Self {
+            n: from_parts(iter.into_iter().collect(), L::empty()),
+        }
+    }
+}
+
+impl<E: Clone> std::iter::IntoIterator for Queue<E> {
+    type Item = E;
+    type IntoIter = QueueIter<E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        QueueIter { queue: self }
     }
 }
 
+impl<E: Clone> std::iter::IntoIterator for &Queue<E> {
+    type Item = E;
+    type IntoIter = QueueIter<E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<E: Clone> std::iter::Extend<E> for Queue<E> {
+    fn extend<I: IntoIterator<Item = E>>(&mut self, iter: I) {
+        *self = self.enqueue_all(iter);
+    }
+}
+
+// two queues are equal if they dequeue the same sequence, whatever their internal front/back split
+impl<E: Clone + PartialEq> PartialEq for Queue<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter_ref().eq(other.iter_ref())
+    }
+}
+
+impl<E: Clone + Eq> Eq for Queue<E> {}
+
 #[cfg(test)]
 mod tests {
     use crate::queue::*;
@@ -183,6 +636,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::needless_range_loop)]
     fn dequeue() {
         let mut elements = Vec::new();
         let mut l = Queue::empty();
@@ -214,4 +668,521 @@ mod tests {
             assert_eq!(queue_elems[i], elements[i + 50000]);
         }
     }
+
+    #[test]
+    fn front() {
+        let q: Queue<i32> = Queue::empty();
+        assert_eq!(q.front(), None);
+
+        let q = q.enqueue(1).enqueue(2).enqueue(3);
+        assert_eq!(q.front(), Some(&1));
+
+        let (_, q) = q.dequeue();
+        assert_eq!(q.front(), Some(&2));
+
+        // the front list gets exhausted while the new element is still in the back list
+        let q = q.enqueue(4);
+        let (_, q) = q.dequeue();
+        let (_, q) = q.dequeue();
+        assert_eq!(q.front(), Some(&4));
+        assert_eq!(q.len(), 1);
+
+        let (e, q) = q.dequeue();
+        assert_eq!(e, 4);
+        assert_eq!(q.front(), None);
+    }
+
+    #[test]
+    fn try_dequeue() {
+        let q: Queue<i32> = Queue::empty();
+        assert!(q.try_dequeue().is_none());
+
+        let q = q.enqueue(1).enqueue(2);
+        let (e, q) = q.try_dequeue().unwrap();
+        assert_eq!(e, 1);
+        let q = q.enqueue(3);
+        let (e, q) = q.try_dequeue().unwrap();
+        assert_eq!(e, 2);
+        let (e, q) = q.try_dequeue().unwrap();
+        assert_eq!(e, 3);
+        assert!(q.try_dequeue().is_none());
+    }
+
+    #[test]
+    fn interleaved() {
+        let mut expected = std::collections::VecDeque::new();
+        let mut q = Queue::empty();
+        for i in 0..1000 {
+            for j in 0..(i % 7) {
+                q = q.enqueue(i * 10 + j);
+                expected.push_back(i * 10 + j);
+            }
+            for _ in 0..(i % 5) {
+                match q.try_dequeue() {
+                    Some((e, n)) => {
+                        assert_eq!(Some(e), expected.pop_front());
+                        q = n;
+                    }
+                    None => assert!(expected.is_empty()),
+                }
+            }
+            assert_eq!(q.len(), expected.len());
+            assert_eq!(q.front(), expected.front());
+        }
+
+        let v = q.to_vec();
+        assert_eq!(v, expected.iter().cloned().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn iter() {
+        let mut q = Queue::empty();
+        for i in 0..100 {
+            q = q.enqueue(i);
+        }
+        let (_, q) = q.dequeue();
+        let q = q.enqueue(100);
+
+        for (i, e) in q.iter().enumerate() {
+            assert_eq!(e, i + 1);
+        }
+        assert_eq!(q.iter().count(), q.len());
+    }
+
+    #[test]
+    fn stress_1000000() {
+        let mut q = Queue::empty();
+        let mut head = 0;
+        let mut tail = 0;
+        for _ in 0..1000000 {
+            if rand() % 3 == 0 && !q.is_empty() {
+                let (e, n) = q.dequeue();
+                assert_eq!(e, head);
+                head += 1;
+                q = n;
+            } else {
+                q = q.enqueue(tail);
+                tail += 1;
+            }
+        }
+
+        assert_eq!(q.len(), tail - head);
+        for e in q.iter() {
+            assert_eq!(e, head);
+            head += 1;
+        }
+        assert_eq!(head, tail);
+    }
+
+    #[test]
+    fn append() {
+        let mut a = Queue::empty();
+        let mut b = Queue::empty();
+        for i in 0..100 {
+            a = a.enqueue(i);
+            b = b.enqueue(i + 1000);
+        }
+        let (_, a) = a.dequeue();
+        let (_, b) = b.dequeue();
+
+        let ab = a.append(&b);
+        assert_eq!(ab.len(), a.len() + b.len());
+        let mut expected = a.to_vec();
+        expected.extend(b.to_vec());
+        assert_eq!(ab.to_vec(), expected);
+
+        let e = Queue::empty();
+        assert_eq!(a.append(&e).to_vec(), a.to_vec());
+        assert_eq!(e.append(&b).to_vec(), b.to_vec());
+        assert!(e.append(&e).is_empty());
+    }
+
+    #[test]
+    fn iter_ref() {
+        let mut q = Queue::empty();
+        for i in 0..100 {
+            q = q.enqueue(format!("{}", i));
+        }
+        for _ in 0..10 {
+            let (_, n) = q.dequeue();
+            q = n;
+        }
+        for i in 100..120 {
+            q = q.enqueue(format!("{}", i));
+        }
+
+        let v = q.iter_ref().collect::<Vec<&String>>();
+        assert_eq!(v.len(), q.len());
+        for (i, e) in v.iter().enumerate() {
+            assert_eq!(**e, format!("{}", i + 10));
+        }
+        assert_eq!(Queue::<String>::empty().iter_ref().count(), 0);
+    }
+
+    #[test]
+    fn map() {
+        let mut q = Queue::empty();
+        for i in 0..100 {
+            q = q.enqueue(i);
+        }
+        let (_, q) = q.dequeue();
+        let q = q.enqueue(100).enqueue(101);
+
+        let mut order = Vec::new();
+        let m = q.map(|e| {
+            order.push(*e);
+            format!("{}", e)
+        });
+        assert_eq!(order, q.to_vec());
+        assert_eq!(m.len(), q.len());
+        assert_eq!(
+            m.to_vec(),
+            q.to_vec()
+                .iter()
+                .map(|e| format!("{}", e))
+                .collect::<Vec<_>>()
+        );
+
+        // the mapped queue keeps working as a queue
+        let m = m.enqueue("102".to_string());
+        let (e, m) = m.dequeue();
+        assert_eq!(e, "1");
+        assert_eq!(m.len(), q.len());
+    }
+
+    #[test]
+    fn filter() {
+        let mut q = Queue::empty();
+        for i in 0..100 {
+            q = q.enqueue(i);
+        }
+        let (_, q) = q.dequeue();
+        let q = q.enqueue(100).enqueue(101);
+
+        let even = q.filter(|e| e % 2 == 0);
+        assert_eq!(even.len(), 50);
+        assert_eq!(
+            even.to_vec(),
+            q.to_vec()
+                .into_iter()
+                .filter(|e| e % 2 == 0)
+                .collect::<Vec<_>>()
+        );
+
+        let none = q.filter(|_| false);
+        assert!(none.is_empty());
+        assert_eq!(none.len(), 0);
+        assert_eq!(none.front(), None);
+        assert_eq!(none.enqueue(7).to_vec(), vec![7]);
+    }
+
+    #[test]
+    fn from_iter() {
+        let v = (0..1000).collect::<Vec<_>>();
+        let q: Queue<i32> = v.clone().into_iter().collect();
+        assert_eq!(q.len(), v.len());
+        assert_eq!(q.front(), Some(&0));
+        assert_eq!(q.to_vec(), v);
+
+        let q = q.enqueue(1000);
+        let (e, q) = q.dequeue();
+        assert_eq!(e, 0);
+        assert_eq!(q.to_vec(), (1..1001).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn into_iter() {
+        let q: Queue<i32> = (0..100).collect();
+
+        let mut count = 0;
+        for (i, e) in (&q).into_iter().enumerate() {
+            assert_eq!(e, i as i32);
+            count += 1;
+        }
+        assert_eq!(count, 100);
+
+        let v = q.into_iter().collect::<Vec<_>>();
+        assert_eq!(v, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn extend() {
+        let q: Queue<i32> = (0..10).collect();
+        let q = q.extended(10..20);
+        assert_eq!(q.to_vec(), (0..20).collect::<Vec<_>>());
+
+        let mut q = q;
+        q.extend(20..30);
+        assert_eq!(q.len(), 30);
+        assert_eq!(q.to_vec(), (0..30).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn eq() {
+        // built by enqueuing only
+        let mut a = Queue::empty();
+        for i in 0..50 {
+            a = a.enqueue(i);
+        }
+
+        // built with a different history: more elements in the front
+        let mut b = Queue::empty();
+        for i in -20..20 {
+            b = b.enqueue(i);
+        }
+        for _ in 0..20 {
+            let (_, n) = b.dequeue();
+            b = n;
+        }
+        for i in 20..50 {
+            b = b.enqueue(i);
+        }
+
+        let c: Queue<i32> = (0..50).collect();
+
+        assert!(a == b);
+        assert!(b == c);
+        assert!(a == c);
+        assert!(a != a.enqueue(50));
+        assert!(a != a.dequeue().1.enqueue(0));
+        assert!(Queue::<i32>::empty() == a.filter(|_| false));
+    }
+
+    #[test]
+    fn split_at() {
+        let mut q = Queue::empty();
+        for i in 0..30 {
+            q = q.enqueue(i);
+        }
+        // move some elements to the front
+        q = q.dequeue().1;
+        for i in 30..40 {
+            q = q.enqueue(i);
+        }
+
+        for i in [0, 1, 10, 38, 39, 100] {
+            let (a, b) = q.split_at(i);
+            assert_eq!(a.len(), i.min(39));
+            assert_eq!(b.len(), 39 - i.min(39));
+            assert_eq!(a.to_vec(), (1..1 + i.min(39) as i32).collect::<Vec<_>>());
+            assert!(a.append(&b) == q);
+            assert_eq!(a.append(&b).to_vec(), q.to_vec());
+        }
+
+        let (a, b) = Queue::<i32>::empty().split_at(5);
+        assert!(a.is_empty() && b.is_empty());
+    }
+
+    #[test]
+    fn rev() {
+        let mut q = Queue::empty();
+        for i in 0..100 {
+            q = q.enqueue(i);
+            let mut expected = q.to_vec();
+            expected.reverse();
+            assert_eq!(q.rev().to_vec(), expected);
+            assert_eq!(q.rev().len(), q.len());
+        }
+
+        // after partial dequeues, with elements in both the front and the back
+        for _ in 0..30 {
+            q = q.dequeue().1;
+        }
+        for i in 100..120 {
+            q = q.enqueue(i);
+        }
+        let mut expected = q.to_vec();
+        expected.reverse();
+        let r = q.rev();
+        assert_eq!(r.to_vec(), expected);
+        assert!(r.rev() == q);
+
+        // the reversed queue is a regular queue
+        let r = r.enqueue(-1).dequeue().1;
+        assert_eq!(r.front(), Some(&118));
+        assert_eq!(r.to_vec().last(), Some(&-1));
+        assert!(Queue::<i32>::empty().rev().is_empty());
+    }
+
+    #[test]
+    fn singleton() {
+        let q = Queue::singleton(3);
+        assert_eq!(q.len(), 1);
+        assert_eq!(q.front(), Some(&3));
+        assert_eq!(q.enqueue(4).to_vec(), vec![3, 4]);
+        assert!(q.dequeue().1.is_empty());
+    }
+
+    #[test]
+    fn back() {
+        let mut q = Queue::empty();
+        assert!(q.back().is_none());
+
+        // enqueues only
+        for i in 0..100 {
+            q = q.enqueue(i);
+            assert_eq!(q.back(), Some(&i));
+            assert_eq!(q.back(), q.to_vec().last());
+        }
+
+        // dequeues, with the back list emptied by the rotations
+        while !q.is_empty() {
+            assert_eq!(q.back(), Some(&99));
+            q = q.dequeue().1;
+        }
+        assert!(q.back().is_none());
+
+        let q: Queue<i32> = (0..10).collect();
+        assert_eq!(q.back(), Some(&9));
+        let q = q.dequeue().1.enqueue(10);
+        assert_eq!(q.back(), Some(&10));
+        assert_eq!(Queue::singleton(1).back(), Some(&1));
+    }
+
+    #[test]
+    fn enqueue_all() {
+        let mut one = Queue::empty();
+        for i in 0..100000 {
+            one = one.enqueue(i);
+        }
+        let bulk = Queue::empty().enqueue_all(0..100000);
+        assert_eq!(bulk.len(), 100000);
+        assert_eq!(bulk.to_vec(), one.to_vec());
+        assert!(bulk == one);
+
+        // on a queue with a pending rotation
+        let mut q = Queue::empty();
+        for i in 0..37 {
+            q = q.enqueue(i);
+        }
+        for _ in 0..5 {
+            q = q.dequeue().1;
+        }
+        let mut one = q.clone();
+        for i in 37..1000 {
+            one = one.enqueue(i);
+        }
+        let mut bulk = q.enqueue_all(37..1000);
+        assert_eq!(bulk.len(), 995);
+        assert!(bulk == one);
+        for i in 5..1000 {
+            let (e, n) = bulk.dequeue();
+            assert_eq!(e, i);
+            bulk = n;
+        }
+        assert!(bulk.is_empty());
+        assert!(q.enqueue_all(std::iter::empty()) == q);
+    }
+
+    #[test]
+    fn split_first_n() {
+        let mut q = Queue::empty();
+        for i in 0..1000 {
+            q = q.enqueue(i);
+        }
+
+        for n in [0, 1, 10, 500, 999, 1000, 5000] {
+            let (v, rest) = q.split_first_n(n);
+            let n = usize::min(n, 1000);
+            assert_eq!(v, (0..n as i32).collect::<Vec<_>>());
+            assert_eq!(rest.len(), 1000 - n);
+            assert_eq!(rest.to_vec(), (n as i32..1000).collect::<Vec<_>>());
+
+            // the remaining queue is a regular queue
+            let rest = rest.enqueue(1000);
+            assert_eq!(rest.len(), 1001 - n);
+            assert_eq!(rest.back(), Some(&1000));
+        }
+
+        // repeated splits
+        let mut a = q.clone();
+        let mut all = Vec::new();
+        while !a.is_empty() {
+            let (v, r) = a.split_first_n(7);
+            assert_eq!(v.len(), usize::min(7, a.len()));
+            all.extend(v);
+            a = r;
+        }
+        assert_eq!(all, (0..1000).collect::<Vec<_>>());
+        assert!(Queue::<i32>::empty().split_first_n(3).0.is_empty());
+    }
+
+    #[test]
+    fn find_contains() {
+        // 0..10 in the front, 10..15 in the back list
+        let q: Queue<i32> = (0..10).collect();
+        let q = q.enqueue_all(10..15);
+        assert_eq!(q.n.front_len, 10);
+        assert_eq!(q.n.back.len(), 5);
+
+        // front portion
+        assert!(q.contains(&3));
+        assert_eq!(q.find(|e| e % 4 == 3), Some(&3));
+        // back portion: the oldest match wins
+        assert!(q.contains(&12));
+        assert_eq!(q.find(|e| *e > 10), Some(&11));
+        assert_eq!(q.find(|e| *e > 10 && e % 2 == 0), Some(&12));
+        // absent
+        assert!(!q.contains(&15));
+        assert_eq!(q.find(|e| *e < 0), None);
+
+        let before = crate::tests::allocations();
+        assert_eq!(q.find(|e| *e == 14), Some(&14));
+        assert_eq!(crate::tests::allocations(), before);
+
+        for i in 0..15 {
+            assert!(q.contains(&i));
+            assert_eq!(q.find(|e| *e >= i), q.iter_ref().find(|e| **e >= i));
+        }
+        assert!(!Queue::<i32>::empty().contains(&0));
+        assert_eq!(Queue::<i32>::empty().find(|_| true), None);
+    }
+
+    #[test]
+    fn fold_reduce() {
+        let mut q = Queue::empty();
+        for i in 0..1000 {
+            q = q.enqueue(i);
+            if i % 3 == 0 {
+                q = q.dequeue().1;
+            }
+        }
+        assert!(!q.n.back.is_empty());
+
+        let v = q.to_vec();
+        assert_eq!(q.fold(0, |acc, e| acc + e), v.iter().sum::<i32>());
+        assert_eq!(
+            q.fold(Vec::new(), |mut acc, e| {
+                acc.push(*e);
+                acc
+            }),
+            v
+        );
+
+        // non commutative: the order matters
+        let expected = v.iter().fold(0i64, |acc, e| acc * 3 % 1000003 + *e as i64);
+        assert_eq!(
+            q.fold(0i64, |acc, e| acc * 3 % 1000003 + *e as i64),
+            expected
+        );
+        let mut manual = v[0];
+        for e in v[1..].iter() {
+            manual = (manual * 7 + e) % 10007;
+        }
+        assert_eq!(q.reduce(|a, b| (a * 7 + b) % 10007), Some(manual));
+
+        assert_eq!(Queue::singleton(5).reduce(|a, b| a + b), Some(5));
+        assert_eq!(Queue::<i32>::empty().reduce(|a, b| a + b), None);
+        assert_eq!(Queue::<i32>::empty().fold(1, |acc, e| acc + e), 1);
+
+        // a long back list is walked without recursing once per element
+        let mut q = Queue::empty();
+        for i in 0..1000000u64 {
+            q = q.enqueue(i);
+        }
+        assert!(q.n.back.len() > 100000);
+        let expected = (0..1000000u64).fold(0u64, |acc, e| acc * 3 % 1000003 + e);
+        assert_eq!(q.fold(0u64, |acc, e| acc * 3 % 1000003 + e), expected);
+        assert_eq!(q.reduce(|a, b| a.max(*b)), Some(999999));
+    }
 }
@@ -27,7 +27,11 @@
 // ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE
 // POSSIBILITY OF SUCH DAMAGE.
 //
-use std::sync::Arc;
+use crate::Arc;
+use std::ops::ControlFlow;
+
+use crate::map::*;
+use crate::{HashSet, Hashable};
 
 #[derive(Clone)]
 enum SetNode<K: Clone> {
@@ -172,6 +176,80 @@ fn find<K: Ord + Clone>(t: &N<K>, k: K) -> Option<&N<K>> {
     }
 }
 
+// build a balanced tree out of the next `n` elements of an ascending iterator
+fn from_sorted_iter<K: Clone, I: Iterator<Item = K>>(n: usize, iter: &mut I) -> N<K> {
+    match n {
+        0 => empty(),
+        _ => {
+            let l = from_sorted_iter(n / 2, iter);
+            let k = iter.next().unwrap();
+            let r = from_sorted_iter(n - n / 2 - 1, iter);
+            make(&l, k, &r)
+        }
+    }
+}
+
+// push `t` and its left spine on the stack of an in-order traversal
+fn push_left<'a, K: Clone>(stack: &mut Vec<&'a SetNode<K>>, t: &'a SetNode<K>) {
+    let mut t = t;
+    loop {
+        match t {
+            Empty => return,
+            One(_) => {
+                stack.push(t);
+                return;
+            }
+            Node(_, l, _, _) => {
+                stack.push(t);
+                t = l;
+            }
+        }
+    }
+}
+
+// seed the stack of an in-order traversal starting at the first key >= `start`: along the search
+// path, only the nodes whose key is >= `start` are pushed
+fn push_from<'a, K: Ord + Clone>(stack: &mut Vec<&'a SetNode<K>>, t: &'a SetNode<K>, start: &K) {
+    let mut t = t;
+    loop {
+        match t {
+            Empty => return,
+            One(k) => {
+                if k >= start {
+                    stack.push(t);
+                }
+                return;
+            }
+            Node(_, l, k, r) => {
+                if k >= start {
+                    stack.push(t);
+                    t = l;
+                } else {
+                    t = r;
+                }
+            }
+        }
+    }
+}
+
+// push `t` and its right spine on the stack of a reverse in-order traversal
+fn push_right<'a, K: Clone>(stack: &mut Vec<&'a SetNode<K>>, t: &'a SetNode<K>) {
+    let mut t = t;
+    loop {
+        match t {
+            Empty => return,
+            One(_) => {
+                stack.push(t);
+                return;
+            }
+            Node(_, _, _, r) => {
+                stack.push(t);
+                t = r;
+            }
+        }
+    }
+}
+
 fn to_vec<K: Ord + Clone>(t: &N<K>, v: &mut Vec<K>) {
     match t.as_ref() {
         Empty => (),
@@ -184,6 +262,22 @@ fn to_vec<K: Ord + Clone>(t: &N<K>, v: &mut Vec<K>) {
     }
 }
 
+fn fold_while<K: Ord + Clone, B, F: FnMut(B, &K) -> ControlFlow<B, B>>(
+    t: &N<K>,
+    acc: B,
+    f: &mut F,
+) -> ControlFlow<B, B> {
+    match t.as_ref() {
+        Empty => ControlFlow::Continue(acc),
+        One(k) => f(acc, k),
+        Node(_, l, k, r) => {
+            let acc = fold_while(l, acc, f)?;
+            let acc = f(acc, k)?;
+            fold_while(r, acc, f)
+        }
+    }
+}
+
 impl<K: Clone> SetNode<K> {
     fn height(&self) -> usize {
         match self {
@@ -192,13 +286,37 @@ impl<K: Clone> SetNode<K> {
             Node(h, _, _, _) => *h,
         }
     }
+
+    fn node_count(&self) -> usize {
+        match self {
+            Empty => 0,
+            One(_) => 1,
+            Node(_, l, _, r) => 1 + l.node_count() + r.node_count(),
+        }
+    }
 }
 
+///
+/// cloning a set is O(1): the clone shares the tree
+///
+#[derive(Clone)]
 pub struct Set<K: Ord + Clone> {
     size: usize,
     n: N<K>,
 }
 
+impl<K: Ord + Clone> From<std::collections::BTreeSet<K>> for Set<K> {
+    fn from(s: std::collections::BTreeSet<K>) -> Self {
+        Self::from_sorted_vec(s.into_iter().collect())
+    }
+}
+
+impl<K: Ord + Clone> Default for Set<K> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
 impl<K: Ord + Clone> Set<K> {
     ///
     /// create and return a new empty set
@@ -210,6 +328,13 @@ impl<K: Ord + Clone> Set<K> {
         }
     }
 
+    ///
+    /// create and return a new set containing only the given key
+    ///
+    pub fn singleton(k: K) -> Self {
+        Self { n: one(k), size: 1 }
+    }
+
     ///
     /// insert a new key and return a new set with the new element added to it
     ///
@@ -232,6 +357,30 @@ impl<K: Ord + Clone> Set<K> {
         Self { n, size }
     }
 
+    ///
+    /// remove the keys of `iter` and return the new set. The batch is sorted, then merged with the
+    /// keys of the set to rebuild the tree once: O(m log(m) + n). The set is returned as is if none
+    /// of the keys exist
+    ///
+    pub fn remove_all<I: IntoIterator<Item = K>>(&self, iter: I) -> Self {
+        let mut batch = iter.into_iter().collect::<Vec<_>>();
+        batch.sort();
+        batch.dedup();
+        let mut b = batch.iter().peekable();
+        let kept = self
+            .iter_ref()
+            .filter(|k| {
+                while b.next_if(|x| x < k).is_some() {}
+                b.next_if(|x| x == k).is_none()
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        match kept.len() == self.size {
+            true => self.clone(),
+            false => Self::from_sorted_vec(kept),
+        }
+    }
+
     ///
     /// search for a key and return true if the key exist, false otherwise
     ///
@@ -255,6 +404,13 @@ impl<K: Ord + Clone> Set<K> {
         self.n.height()
     }
 
+    ///
+    /// return the number of tree nodes, for debugging memory use: sets sharing nodes count them each
+    ///
+    pub fn node_count(&self) -> usize {
+        self.n.node_count()
+    }
+
     ///
     /// return true if the set is empty
     ///
@@ -268,6 +424,221 @@ impl<K: Ord + Clone> Set<K> {
     pub fn len(&self) -> usize {
         self.size
     }
+
+    ///
+    /// create and return a new set holding only the keys for which `f` returns true.
+    /// The keys are walked once in order and the tree is rebuilt balanced: O(n)
+    ///
+    pub fn retain<F: Fn(&K) -> bool>(&self, f: F) -> Self {
+        self.retain_counted(f).0
+    }
+
+    ///
+    /// same as `retain`, also returning the number of keys removed
+    ///
+    pub fn retain_counted<F: Fn(&K) -> bool>(&self, f: F) -> (Self, usize) {
+        let kept = self
+            .iter_ref()
+            .filter(|k| f(k))
+            .cloned()
+            .collect::<Vec<_>>();
+        let removed = self.size - kept.len();
+        (Self::from_sorted_vec(kept), removed)
+    }
+
+    ///
+    /// partition the keys into buckets: create and return a map from each `key(k)` to the set of
+    /// keys sharing it
+    ///
+    pub fn group_by<B: Ord + Clone, F: FnMut(&K) -> B>(&self, mut key: F) -> Map<B, Set<K>> {
+        let mut buckets = std::collections::BTreeMap::new();
+        for k in self.to_vec() {
+            buckets.entry(key(&k)).or_insert_with(Vec::new).push(k);
+        }
+        Map::from_sorted_vec(
+            buckets
+                .into_iter()
+                .map(|(b, keys)| (b, Set::from_sorted_vec(keys)))
+                .collect(),
+        )
+    }
+
+    ///
+    /// returns an iterator over references to the keys, in ascending order. Nothing is cloned:
+    /// the traversal stack (sized to the height of the tree) is the only allocation
+    ///
+    pub fn iter_ref(&self) -> impl Iterator<Item = &K> + Clone + '_ {
+        let mut stack = Vec::with_capacity(self.n.height());
+        push_left(&mut stack, self.n.as_ref());
+        std::iter::from_fn(move || match stack.pop()? {
+            Empty => None,
+            One(k) => Some(k),
+            Node(_, _, k, r) => {
+                push_left(&mut stack, r.as_ref());
+                Some(k)
+            }
+        })
+    }
+
+    ///
+    /// returns an iterator over successive chunks of (at most) `n` keys, in ascending order. The last
+    /// chunk may be shorter. Panics if `n` is 0
+    ///
+    pub fn chunks(&self, n: usize) -> impl Iterator<Item = Vec<K>> + '_ {
+        assert!(n != 0, "chunks: the chunk size must be non-zero");
+        let mut iter = self.iter_ref();
+        std::iter::from_fn(move || {
+            let chunk = iter.by_ref().take(n).cloned().collect::<Vec<_>>();
+            match chunk.is_empty() {
+                true => None,
+                false => Some(chunk),
+            }
+        })
+    }
+
+    ///
+    /// returns an iterator over references to the keys >= `start`, in ascending order. The traversal
+    /// is seeded along the search path to `start`: O(log n) to set up
+    ///
+    pub fn iter_from<'a>(&'a self, start: &K) -> impl Iterator<Item = &'a K> + 'a {
+        let mut stack = Vec::with_capacity(self.n.height());
+        push_from(&mut stack, self.n.as_ref(), start);
+        std::iter::from_fn(move || match stack.pop()? {
+            Empty => None,
+            One(k) => Some(k),
+            Node(_, _, k, r) => {
+                push_left(&mut stack, r.as_ref());
+                Some(k)
+            }
+        })
+    }
+
+    ///
+    /// returns an iterator over references to the keys, in descending order (the mirror image of
+    /// `iter_ref`)
+    ///
+    pub fn iter_rev(&self) -> impl Iterator<Item = &K> + '_ {
+        let mut stack = Vec::with_capacity(self.n.height());
+        push_right(&mut stack, self.n.as_ref());
+        std::iter::from_fn(move || match stack.pop()? {
+            Empty => None,
+            One(k) => Some(k),
+            Node(_, l, k, _) => {
+                push_right(&mut stack, l.as_ref());
+                Some(k)
+            }
+        })
+    }
+
+    ///
+    /// create and return a std `BTreeSet` holding the same keys
+    ///
+    pub fn to_std(&self) -> std::collections::BTreeSet<K> {
+        self.iter_ref().cloned().collect()
+    }
+
+    ///
+    /// create and return a hash set holding the same keys
+    ///
+    pub fn to_hashset(&self) -> HashSet<K>
+    where
+        K: Hashable,
+    {
+        self.to_vec()
+            .into_iter()
+            .fold(HashSet::empty(), |s, k| s.insert(k))
+    }
+
+    ///
+    /// create and return a new set out of strictly ascending (sorted, without duplicates) keys.
+    /// The tree is built bottom-up, balanced: O(n)
+    ///
+    pub fn from_sorted_vec(v: Vec<K>) -> Self {
+        debug_assert!(
+            v.windows(2).all(|w| w[0] < w[1]),
+            "from_sorted_vec: the keys must be strictly ascending"
+        );
+        let size = v.len();
+        Self {
+            n: from_sorted_iter(size, &mut v.into_iter()),
+            size,
+        }
+    }
+
+    ///
+    /// fold the keys in ascending order, stopping as soon as `f` returns `ControlFlow::Break`.
+    /// Returns the value carried by the `Break`, or the final accumulator
+    ///
+    pub fn fold_while<B, F: FnMut(B, &K) -> ControlFlow<B, B>>(&self, init: B, mut f: F) -> B {
+        match fold_while(&self.n, init, &mut f) {
+            ControlFlow::Continue(b) => b,
+            ControlFlow::Break(b) => b,
+        }
+    }
+}
+
+impl<K: Ord + Clone> std::iter::IntoIterator for Set<K> {
+    type Item = K;
+    type IntoIter = SetIntoIter<K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut iter = SetIntoIter { stack: Vec::new() };
+        iter.push_left(self.n);
+        iter
+    }
+}
+
+///
+/// consuming iterator (ascending order): the keys of the nodes owned only by the set are moved out,
+/// those of the nodes shared with other sets are cloned
+///
+#[derive(Clone)]
+pub struct SetIntoIter<K: Clone> {
+    // the pending keys, each with the right subtree to visit after it
+    stack: Vec<(K, Option<N<K>>)>,
+}
+
+impl<K: Clone> SetIntoIter<K> {
+    fn push_left(&mut self, t: N<K>) {
+        let mut t = t;
+        loop {
+            let next = match Arc::try_unwrap(t) {
+                Ok(Empty) => return,
+                Ok(One(k)) => {
+                    self.stack.push((k, None));
+                    return;
+                }
+                Ok(Node(_, l, k, r)) => {
+                    self.stack.push((k, Some(r)));
+                    l
+                }
+                Err(t) => match t.as_ref() {
+                    Empty => return,
+                    One(k) => {
+                        self.stack.push((k.clone(), None));
+                        return;
+                    }
+                    Node(_, l, k, r) => {
+                        self.stack.push((k.clone(), Some(r.clone())));
+                        l.clone()
+                    }
+                },
+            };
+            t = next;
+        }
+    }
+}
+
+impl<K: Clone> std::iter::Iterator for SetIntoIter<K> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (k, r) = self.stack.pop()?;
+        if let Some(r) = r {
+            self.push_left(r);
+        }
+        Some(k)
+    }
 }
 
 #[cfg(test)]
@@ -302,6 +673,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn find() {
         let numbers = [5, 10, 3, 120, 4, 9, 27, 1, 45];
         let mut n = Set::empty();
@@ -336,6 +708,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn remove_one_from_one() {
         let mut n = Set::empty();
         n = n.insert(10);
@@ -380,6 +753,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
     fn remove_5000_from_10000_random() {
         let mut hs = std::collections::hash_set::HashSet::new();
         let mut numbers = Vec::new();
@@ -424,4 +798,380 @@ mod tests {
         assert_eq!(n.exist(numbers[0]), false);
         assert_eq!(n.to_vec().len(), hs.len());
     }
+
+    #[test]
+    fn fold_while() {
+        let mut n = Set::empty();
+        for i in 1..=100 {
+            n = n.insert(i);
+        }
+
+        let mut visited = Vec::new();
+        let sum = n.fold_while(0, |acc, k| {
+            visited.push(*k);
+            match acc + k {
+                s if s > 50 => ControlFlow::Break(acc),
+                s => ControlFlow::Continue(s),
+            }
+        });
+
+        // 1 + 2 + ... + 9 = 45, adding 10 would exceed the threshold
+        assert_eq!(sum, 45);
+        assert_eq!(visited, (1..=10).collect::<Vec<_>>());
+
+        let total = n.fold_while(0, |acc, k| ControlFlow::Continue(acc + k));
+        assert_eq!(total, 5050);
+
+        let e = Set::<i32>::empty();
+        assert_eq!(e.fold_while(7, |_, _| ControlFlow::Break(0)), 7);
+    }
+
+    #[test]
+    fn retain() {
+        let mut n = Set::empty();
+        for _ in 0..10000 {
+            n = n.insert(rand());
+        }
+
+        for m in 1..10 {
+            let (r, removed) = n.retain_counted(|k| k % m == 0);
+            assert_eq!(removed, n.len() - r.len());
+            let expected = n
+                .iter_ref()
+                .filter(|k| *k % m == 0)
+                .cloned()
+                .collect::<Vec<_>>();
+            assert_eq!(r.to_vec(), expected);
+            assert_eq!(n.retain(|k| k % m == 0).to_vec(), expected);
+        }
+
+        let threshold = rand();
+        let (r, removed) = n.retain_counted(|k| *k < threshold);
+        assert_eq!(removed, n.len() - r.len());
+        assert!(r.iter_ref().all(|k| *k < threshold));
+
+        assert_eq!(n.retain_counted(|_| true).1, 0);
+        assert_eq!(n.retain_counted(|_| false).1, n.len());
+        assert!(n.retain(|_| false).is_empty());
+        assert_eq!(Set::<i32>::empty().retain_counted(|_| true).1, 0);
+    }
+
+    #[test]
+    fn group_by() {
+        let mut n = Set::empty();
+        for i in 0..100 {
+            n = n.insert(i);
+        }
+
+        let g = n.group_by(|k| k % 2 == 0);
+        assert_eq!(g.len(), 2);
+
+        let even = g.find(true).unwrap();
+        let odd = g.find(false).unwrap();
+        assert_eq!(even.len(), 50);
+        assert_eq!(odd.len(), 50);
+        for i in 0..100 {
+            assert_eq!(even.exist(i), i % 2 == 0);
+            assert_eq!(odd.exist(i), i % 2 == 1);
+        }
+        assert_eq!(even.to_vec(), (0..100).step_by(2).collect::<Vec<_>>());
+
+        assert!(Set::<i32>::empty().group_by(|k| *k).is_empty());
+    }
+
+    #[test]
+    fn to_hashset() {
+        let mut s = Set::empty();
+        for i in 0..10000usize {
+            s = s.insert(i * 7 % 10007);
+        }
+        let h = s.to_hashset();
+        assert_eq!(h.len(), s.len());
+        for k in s.to_vec() {
+            assert!(h.exist(k));
+        }
+
+        let back = h.to_ordered();
+        assert_eq!(back.len(), s.len());
+        assert_eq!(back.to_vec(), s.to_vec());
+        assert!(Set::<usize>::empty().to_hashset().is_empty());
+    }
+
+    #[test]
+    fn from_sorted_vec() {
+        for n in [0, 1, 2, 3, 7, 8, 1000, 100000] {
+            let mut v = Vec::new();
+            let mut inserted = Set::empty();
+            for _ in 0..n {
+                let r = rand();
+                v.push(r);
+                inserted = inserted.insert(r);
+            }
+            v.sort();
+            v.dedup();
+
+            let s = Set::from_sorted_vec(v.clone());
+            assert_eq!(s.len(), v.len());
+            assert_eq!(s.to_vec(), v);
+            assert_eq!(s.to_vec(), inserted.to_vec());
+            assert!(s.height() <= inserted.height());
+            for k in v.iter() {
+                assert!(s.exist(*k));
+            }
+
+            // the set is usable as any other one
+            let s = s.insert(i32::MAX).remove(i32::MAX);
+            assert_eq!(s.to_vec(), v);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn from_sorted_vec_not_sorted() {
+        Set::from_sorted_vec(vec![1, 3, 2]);
+    }
+
+    #[test]
+    fn drop_1000000_sequential() {
+        // the tree stays balanced, so dropping it recursively only goes log(n) deep
+        let mut s = Set::empty();
+        for i in 0..1000000 {
+            s = s.insert(i);
+        }
+        assert!(s.height() <= 28);
+        drop(s);
+    }
+
+    #[test]
+    fn singleton() {
+        let s = Set::singleton(3);
+        assert_eq!(s.len(), 1);
+        assert_eq!(s.height(), 1);
+        assert!(s.exist(3));
+        assert!(!s.exist(4));
+        assert_eq!(s.insert(4).to_vec(), vec![3, 4]);
+        assert!(s.remove(3).is_empty());
+    }
+
+    #[test]
+    fn iter_ref() {
+        let mut v = Vec::new();
+        for _ in 0..100000 {
+            v.push(format!("{:08}", rand()));
+        }
+        v.sort();
+        v.dedup();
+        let s = Set::from_sorted_vec(v.clone());
+
+        let mut iter = s.iter_ref();
+        let before = crate::tests::allocations();
+        let mut count = 0;
+        let mut prev: Option<&String> = None;
+        for k in &mut iter {
+            assert!(prev.is_none_or(|p| p < k));
+            prev = Some(k);
+            count += 1;
+        }
+        assert_eq!(crate::tests::allocations(), before);
+        assert_eq!(count, v.len());
+        assert!(s.iter_ref().eq(v.iter()));
+
+        assert!(Set::singleton(1).iter_ref().eq([1].iter()));
+        assert_eq!(Set::<i32>::empty().iter_ref().count(), 0);
+    }
+
+    #[test]
+    fn iter_rev() {
+        let mut hs = std::collections::HashSet::new();
+        for _ in 0..10000 {
+            hs.insert(rand());
+        }
+
+        let mut n = Set::empty();
+        for i in hs.iter() {
+            n = n.insert(*i);
+        }
+
+        let mut sorted = hs.into_iter().collect::<Vec<_>>();
+        sorted.sort();
+        sorted.reverse();
+        let v = n.iter_rev().cloned().collect::<Vec<_>>();
+        assert_eq!(v, sorted);
+        assert!(v.windows(2).all(|w| w[0] > w[1]));
+        assert!(n
+            .iter_rev()
+            .eq(n.iter_ref().collect::<Vec<_>>().into_iter().rev()));
+
+        assert!(Set::singleton(1).iter_rev().eq([1].iter()));
+        assert_eq!(Set::<i32>::empty().iter_rev().count(), 0);
+    }
+
+    #[test]
+    fn iter_from() {
+        let s = Set::from_sorted_vec((0..1000).map(|i| i * 2).collect());
+
+        // present key
+        assert!(s
+            .iter_from(&500)
+            .eq((250..1000).map(|i| i * 2).collect::<Vec<_>>().iter()));
+        // absent key, between two present ones
+        assert!(s
+            .iter_from(&501)
+            .eq((251..1000).map(|i| i * 2).collect::<Vec<_>>().iter()));
+        // below the minimum, above the maximum
+        assert!(s.iter_from(&-10).eq(s.iter_ref()));
+        assert_eq!(s.iter_from(&1998).collect::<Vec<_>>(), vec![&1998]);
+        assert_eq!(s.iter_from(&1999).count(), 0);
+
+        let mut n = Set::empty();
+        for _ in 0..10000 {
+            n = n.insert(rand());
+        }
+        for _ in 0..100 {
+            let start = rand();
+            assert!(n
+                .iter_from(&start)
+                .eq(n.iter_ref().filter(|k| **k >= start)));
+        }
+
+        assert_eq!(Set::singleton(1).iter_from(&1).count(), 1);
+        assert_eq!(Set::<i32>::empty().iter_from(&0).count(), 0);
+    }
+
+    #[test]
+    fn clone_default() {
+        let s: Set<i32> = Default::default();
+        assert!(s.is_empty());
+
+        let mut s = s;
+        for i in 0..1000 {
+            s = s.insert(i);
+        }
+        let c = s.clone();
+        assert!(Arc::ptr_eq(&c.n, &s.n));
+        assert_eq!(c.len(), s.len());
+
+        let c = c.insert(1000).remove(0);
+        assert_eq!(s.len(), 1000);
+        assert!(s.exist(0));
+        assert!(!s.exist(1000));
+        assert_eq!(c.len(), 1000);
+        assert!(!c.exist(0));
+    }
+
+    #[test]
+    fn std_conversions() {
+        let mut std_set = std::collections::BTreeSet::new();
+        for _ in 0..10000 {
+            std_set.insert(rand());
+        }
+
+        let s = Set::from(std_set.clone());
+        assert_eq!(s.len(), std_set.len());
+        assert!(s.iter_ref().eq(std_set.iter()));
+
+        let back = s.to_std();
+        assert_eq!(back, std_set);
+        assert!(back.iter().eq(s.iter_ref()));
+
+        assert!(Set::<i32>::from(std::collections::BTreeSet::new()).is_empty());
+        assert!(Set::<i32>::empty().to_std().is_empty());
+    }
+
+    #[test]
+    fn node_count() {
+        let s = Set::from_sorted_vec((0..1000).collect());
+        assert_eq!(s.node_count(), 1000);
+        assert_eq!(s.insert(1000).node_count(), 1001);
+        assert_eq!(s.remove(0).node_count(), 999);
+        assert_eq!(Set::singleton(1).node_count(), 1);
+        assert_eq!(Set::<i32>::empty().node_count(), 0);
+    }
+
+    #[test]
+    fn chunks() {
+        let s = Set::from_sorted_vec((0..1000).collect());
+        let chunks = s.chunks(256).collect::<Vec<_>>();
+        assert_eq!(
+            chunks.iter().map(|c| c.len()).collect::<Vec<_>>(),
+            vec![256, 256, 256, 232]
+        );
+        assert_eq!(chunks.concat(), s.to_vec());
+        for (i, c) in chunks.iter().enumerate() {
+            assert_eq!(c[0], i as i32 * 256);
+        }
+
+        assert_eq!(s.chunks(1000).count(), 1);
+        assert_eq!(s.chunks(1).count(), 1000);
+        assert_eq!(Set::<i32>::empty().chunks(3).count(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn chunks_zero() {
+        let _ = Set::singleton(1).chunks(0);
+    }
+
+    #[test]
+    fn into_iter() {
+        let mut v = Vec::new();
+        for _ in 0..100000 {
+            v.push(format!("{:08}", rand()));
+        }
+        v.sort();
+        v.dedup();
+
+        // sole owner: the keys are moved out
+        let s = Set::from_sorted_vec(v.clone());
+        let mut count = 0;
+        for (k, e) in s.into_iter().zip(v.iter()) {
+            assert_eq!(&k, e);
+            count += 1;
+        }
+        assert_eq!(count, v.len());
+
+        // shared: the keys of the shared nodes are cloned, the other set is untouched
+        let s = Set::from_sorted_vec(v.clone());
+        let shared = s.insert(String::from("~")).remove(v[0].clone());
+        let drained = shared.clone().into_iter().collect::<Vec<_>>();
+        assert_eq!(drained, shared.to_vec());
+        assert_eq!(s.into_iter().collect::<Vec<_>>(), v);
+        assert_eq!(shared.len(), v.len());
+        assert_eq!(shared.iter_ref().last().unwrap(), "~");
+
+        let mut sum = 0;
+        for k in Set::from_sorted_vec((0..100).collect()) {
+            sum += k;
+        }
+        assert_eq!(sum, 4950);
+        assert_eq!(Set::<i32>::empty().into_iter().next(), None);
+    }
+
+    #[test]
+    fn remove_all() {
+        let mut reference = std::collections::BTreeSet::new();
+        while reference.len() < 10000 {
+            reference.insert(rand());
+        }
+        let s = Set::from_sorted_vec(reference.iter().cloned().collect());
+
+        let mut batch = reference.iter().cloned().step_by(2).collect::<Vec<_>>();
+        // absent keys and duplicates are skipped
+        batch.push(batch[0]);
+        batch.extend((0..10).map(|_| rand()).filter(|k| !reference.contains(k)));
+        for k in batch.iter() {
+            reference.remove(k);
+        }
+
+        let r = s.remove_all(batch);
+        assert_eq!(r.len(), 5000);
+        assert_eq!(r.len(), reference.len());
+        assert!(r.iter_ref().eq(reference.iter()));
+        assert_eq!(s.len(), 10000);
+
+        assert!(s.remove_all(std::iter::empty()).iter_ref().eq(s.iter_ref()));
+        assert!(s.remove_all(s.to_vec()).is_empty());
+        assert!(Set::<i32>::empty().remove_all(vec![1, 2]).is_empty());
+    }
 }
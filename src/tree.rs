@@ -1,5 +1,6 @@
-use crate::{HashSet, Hashable};
-use std::{ops::Deref, sync::Arc};
+use crate::Arc;
+use crate::{HashSet, Hashable, Queue};
+use std::ops::Deref;
 
 pub trait TreeAcc<D: Clone> {
     fn push(&mut self, data: &D);
@@ -15,12 +16,38 @@ struct NodePriv<D: Clone> {
     children: HashSet<Node<D>>,
 }
 
+// drop the children iteratively: the default (recursive) drop would overflow the stack on deep trees.
+// The subtrees still shared with other trees are left alone
+impl<D: Clone> Drop for NodePriv<D> {
+    fn drop(&mut self) {
+        if self.children.is_empty() {
+            return;
+        }
+
+        let mut stack = std::mem::replace(&mut self.children, HashSet::empty())
+            .iter()
+            .collect::<Vec<_>>();
+        while let Some(n) = stack.pop() {
+            if let Ok(mut n) = Arc::try_unwrap(n.0) {
+                if !n.children.is_empty() {
+                    stack.extend(std::mem::replace(&mut n.children, HashSet::empty()).iter());
+                }
+            }
+        }
+    }
+}
+
+// nodes are identified by pointer (as `Identity` does): the hash is the address of the node, so it
+// changes from one run to another, and a copy of a node (see `copy`) never hashes like the original
 impl<D: Clone> Hashable for Node<D> {
     fn hash(&self) -> u64 {
         Arc::as_ptr(&self.0) as usize as u64
     }
 }
 
+// canonical (height, rank) of the nodes of a subtree, by node address
+type Ranks<D> = std::collections::HashMap<*const NodePriv<D>, (usize, usize)>;
+
 impl<D: Clone> PartialEq for Node<D> {
     fn eq(&self, other: &Self) -> bool {
         Arc::ptr_eq(&self.0, &other.0)
@@ -38,10 +65,135 @@ impl<D: Clone> Node<D> {
         self.0.children.iter()
     }
 
+    // depth first, pre-order walk over the subtree (same order as Path::iter_preorder)
+    fn iter_preorder(&self) -> impl Iterator<Item = Node<D>> {
+        let mut stack = vec![self.clone()];
+        std::iter::from_fn(move || {
+            let n = stack.pop()?;
+            let first = stack.len();
+            stack.extend(n.iter_children());
+            stack[first..].reverse();
+            Some(n)
+        })
+    }
+
     fn new(data: D, children: HashSet<Node<D>>) -> Self {
         Self(Arc::new(NodePriv { data, children }))
     }
 
+    // compares the data and the children (as a multiset) recursively. Children of `other` are
+    // matched at most once, so duplicated subtrees must appear the same number of times
+    fn structurally_eq(&self, other: &Self) -> bool
+    where
+        D: PartialEq,
+    {
+        if Arc::ptr_eq(&self.0, &other.0) {
+            return true;
+        }
+        if self.data() != other.data() || self.0.children.len() != other.0.children.len() {
+            return false;
+        }
+
+        let mut others = other.iter_children().map(Some).collect::<Vec<_>>();
+        self.iter_children().all(|c| {
+            let pos = others
+                .iter()
+                .position(|o| o.as_ref().is_some_and(|o| c.structurally_eq(o)));
+            match pos {
+                Some(i) => {
+                    others[i] = None;
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+
+    // canonical (height, rank) of every distinct node of the subtree, computed bottom-up with an
+    // explicit stack. The nodes of a given height are ranked by data, then by the sorted (height,
+    // rank) of their children: two nodes get the same pair only if their subtrees are identical
+    fn canonical_ranks(&self) -> Ranks<D>
+    where
+        D: Ord,
+    {
+        let mut heights = std::collections::HashMap::new();
+        let mut levels: Vec<Vec<Node<D>>> = Vec::new();
+        let mut stack = vec![(self.clone(), false)];
+        while let Some((n, expanded)) = stack.pop() {
+            let p = Arc::as_ptr(&n.0);
+            if heights.contains_key(&p) {
+                continue;
+            }
+            match expanded {
+                false => {
+                    stack.push((n.clone(), true));
+                    stack.extend(
+                        n.iter_children()
+                            .filter(|c| !heights.contains_key(&Arc::as_ptr(&c.0)))
+                            .map(|c| (c, false)),
+                    );
+                }
+                true => {
+                    let h = n
+                        .iter_children()
+                        .map(|c| heights[&Arc::as_ptr(&c.0)] + 1)
+                        .max()
+                        .unwrap_or(0);
+                    heights.insert(p, h);
+                    if levels.len() <= h {
+                        levels.resize(h + 1, Vec::new());
+                    }
+                    levels[h].push(n);
+                }
+            }
+        }
+
+        let mut ranks = Ranks::new();
+        for (h, level) in levels.iter().enumerate() {
+            let mut keys = level
+                .iter()
+                .map(|n| {
+                    let mut children = n
+                        .iter_children()
+                        .map(|c| ranks[&Arc::as_ptr(&c.0)])
+                        .collect::<Vec<_>>();
+                    children.sort();
+                    (n.data(), children, Arc::as_ptr(&n.0))
+                })
+                .collect::<Vec<_>>();
+            keys.sort_by(|a, b| a.0.cmp(b.0).then_with(|| a.1.cmp(&b.1)));
+
+            let mut rank = 0;
+            for (i, (d, children, p)) in keys.iter().enumerate() {
+                if i > 0 && (keys[i - 1].0, &keys[i - 1].1) != (*d, children) {
+                    rank += 1;
+                }
+                ranks.insert(*p, (h, rank));
+            }
+        }
+        ranks
+    }
+
+    // the children ordered by data, then by their canonical (height, rank) in `ranks`
+    fn sorted_children(&self, ranks: &Ranks<D>) -> Vec<Self>
+    where
+        D: Ord,
+    {
+        let mut children = self.iter_children().collect::<Vec<_>>();
+        children.sort_by(|a, b| {
+            a.data()
+                .cmp(b.data())
+                .then_with(|| ranks[&Arc::as_ptr(&a.0)].cmp(&ranks[&Arc::as_ptr(&b.0)]))
+        });
+        children
+    }
+
+    // returns a new node sharing the data and the children of `self`. Nodes are identified by
+    // pointer, so the copy can't collide with `self` (or another copy) in a children set
+    fn copy(&self) -> Self {
+        Self::new(self.data().clone(), self.0.children.clone())
+    }
+
     fn apply<F: FnOnce(&D) -> Option<D>>(&self, f: F) -> Option<Self> {
         let new_data = f(self.data());
         new_data.map(|data| {
@@ -52,11 +204,34 @@ impl<D: Clone> Node<D> {
         })
     }
 
+    // applies `f` to the data of the direct children only. Returns None if no child changed
+    fn map_children<F: FnMut(&D) -> Option<D>>(&self, f: &mut F) -> Option<Self> {
+        let mut changed = false;
+        let mut children = HashSet::empty();
+        for c in self.iter_children() {
+            let child = match c.apply(&mut *f) {
+                Some(c) => {
+                    changed = true;
+                    c
+                }
+                None => c,
+            };
+            children = children.insert(child);
+        }
+
+        match changed {
+            true => Some(Node::new(self.data().clone(), children)),
+            false => None,
+        }
+    }
+
     fn apply_recursive<F: FnMut(&D) -> Option<D>>(&self, f: &mut F) -> Option<Self> {
         let mut changed = false;
         let mut children = HashSet::empty();
 
-        // TODO: using while let Some(c) = self.0.children.iter() seems to make this hangs: Investigate!!!!
+        // the old children set is persistent: the new children go to a separate set, so each child
+        // is visited exactly once. Note: `while let Some(c) = self.0.children.iter().next()` would
+        // restart a new iterator (and return the first child) at every step, and never end
         for c in self.0.children.iter() {
             let child = match c.apply_recursive(f) {
                 Some(c) => {
@@ -99,7 +274,7 @@ impl<D: Clone> Node<D> {
         acc.push(self.data());
         let new_data = f(acc, self.data());
 
-        // TODO: using while let Some(c) = self.0.children.iter() seems to make this hangs: Investigate!!!!
+        // each child is visited exactly once (see apply_recursive)
         for c in self.0.children.iter() {
             let child = match c.apply_acc_recursive(acc, f) {
                 Some(c) => {
@@ -138,9 +313,8 @@ impl<D: Clone> Node<D> {
             true => {
                 let mut children = HashSet::empty();
                 for c in self.iter_children() {
-                    match c.filter_recursive(f.clone()) {
-                        Some(cc) => children = children.insert(cc),
-                        None => (),
+                    if let Some(cc) = c.filter_recursive(f.clone()) {
+                        children = children.insert(cc)
                     }
                 }
                 Some(Self(Arc::new(NodePriv {
@@ -152,6 +326,89 @@ impl<D: Clone> Node<D> {
         }
     }
 
+    ///
+    /// Returns a new tree without the descendants (and their subtrees) for which the given
+    /// predicate "f" returns "true", or None if nothing was removed
+    ///
+    fn prune<F: Fn(&D) -> bool>(&self, f: &F) -> Option<Self> {
+        let kept = self.0.children.filter(|c| !f(c.data()));
+        let mut changed = kept.len() != self.0.children.len();
+        let mut children = HashSet::empty();
+        for c in kept.iter() {
+            let child = match c.prune(f) {
+                Some(c) => {
+                    changed = true;
+                    c
+                }
+                None => c,
+            };
+            children = children.insert(child);
+        }
+
+        match changed {
+            true => Some(Node::new(self.data().clone(), children)),
+            false => None,
+        }
+    }
+
+    ///
+    /// Returns a new tree without the nodes deeper than `max_depth` (this node being at depth 0),
+    /// or None if nothing was removed
+    ///
+    fn truncate_depth(&self, max_depth: usize) -> Option<Self> {
+        if self.0.children.is_empty() {
+            return None;
+        }
+        if max_depth == 0 {
+            return Some(Node::new(self.data().clone(), HashSet::empty()));
+        }
+
+        let mut changed = false;
+        let mut children = HashSet::empty();
+        for c in self.iter_children() {
+            let child = match c.truncate_depth(max_depth - 1) {
+                Some(c) => {
+                    changed = true;
+                    c
+                }
+                None => c,
+            };
+            children = children.insert(child);
+        }
+
+        match changed {
+            true => Some(Node::new(self.data().clone(), children)),
+            false => None,
+        }
+    }
+
+    ///
+    /// Returns a new tree with the data of every node mapped through "f". `path` is the part of a
+    /// path going through this node (starting with it, if not empty), the new nodes along it are
+    /// stored in `mapped`
+    ///
+    fn map_tree<W: Clone, F: FnMut(&D) -> W>(
+        &self,
+        f: &mut F,
+        path: &[Node<D>],
+        mapped: &mut [Option<Node<W>>],
+    ) -> Node<W> {
+        let data = f(self.data());
+        let children = self.0.children.map(|c| {
+            if path.len() > 1 && path[1] == *c {
+                c.map_tree(f, &path[1..], &mut mapped[1..])
+            } else {
+                c.map_tree(f, &[], &mut [])
+            }
+        });
+
+        let n = Node::new(data, children);
+        if !path.is_empty() {
+            mapped[0] = Some(n.clone());
+        }
+        n
+    }
+
     pub fn map_data<F: FnMut(&D) -> Option<D>>(&self, f: &mut F) -> Option<Self> {
         let mut children: HashSet<Node<D>> = HashSet::empty();
         let mut children_changed = false;
@@ -198,7 +455,7 @@ impl<D: Clone> PathPriv<D> {
     }
 
     pub fn add_node(&self, data: D) -> Arc<Self> {
-        assert!(self.node_vec.len() >= 1);
+        assert!(!self.node_vec.is_empty());
         for i in 1..self.node_vec.len() {
             assert!(self.node_vec[i - 1]
                 .0
@@ -356,20 +613,77 @@ impl<D: Clone> Path<D> {
         self.path.node_vec.last().unwrap().data()
     }
 
+    ///
+    /// returns the data of the root, without building a new path like `root().data()` does
+    ///
+    pub fn root_data(&self) -> &D {
+        self.path.node_vec[0].data()
+    }
+
+    ///
+    /// sets the data of the root (keeping its children), the focus staying on the current node.
+    /// Nodes don't refer to their parent, so only the root is rebuilt: the rest of the path is shared
+    ///
+    pub fn set_root_data(&self, data: D) -> Self {
+        let mut node_vec = self.path.node_vec.clone();
+        node_vec[0] = Node::new(data, node_vec[0].0.children.clone());
+        Self {
+            path: Arc::new(PathPriv { node_vec }),
+        }
+    }
+
+    ///
+    /// returns true if the current node is the root
+    ///
+    pub fn is_root(&self) -> bool {
+        self.path.node_vec.len() == 1
+    }
+
+    ///
+    /// returns true if the current node has no children
+    ///
+    pub fn is_leaf(&self) -> bool {
+        self.child_count() == 0
+    }
+
+    ///
+    /// returns the number of children of the current node (same as `children().len()`, without
+    /// building the paths)
+    ///
+    pub fn child_count(&self) -> usize {
+        self.path.node_vec.last().unwrap().0.children.len()
+    }
+
+    fn child(&self, c: Node<D>) -> Self {
+        let mut new_path = self.path.node_vec.clone();
+        new_path.push(c);
+
+        Self {
+            path: Arc::new(PathPriv { node_vec: new_path }),
+        }
+    }
+
     pub fn children(&self) -> Vec<Self> {
         let mut res = Vec::new();
         let iter = self.path.node_vec.last().unwrap().iter_children();
         for c in iter {
-            let mut new_path = self.path.node_vec.clone();
-            new_path.push(c);
-
-            res.push(Self {
-                path: Arc::new(PathPriv { node_vec: new_path }),
-            });
+            res.push(self.child(c));
         }
         res
     }
 
+    ///
+    /// returns the path to the first child whose data satisfies `f`, None if there's none.
+    /// The children are visited in the order of the underlying hash set (same as `children()`)
+    ///
+    pub fn find_child<F: Fn(&D) -> bool>(&self, f: F) -> Option<Self> {
+        self.path
+            .node()
+            .iter_children()
+            .find(|c| f(c.data()))
+            .map(|c| self.child(c))
+    }
+
     pub fn parent(&self) -> Self {
         let len = self.path.node_vec.len();
         let parent_path = Vec::from(&self.path.node_vec[0..len - 1]);
@@ -380,12 +694,101 @@ impl<D: Clone> Path<D> {
         }
     }
 
+    ///
+    /// returns the other children of the parent, in the order of `children()`.
+    /// The root has no siblings
+    ///
+    pub fn siblings(&self) -> Vec<Self> {
+        match self.len() {
+            1 => Vec::new(),
+            _ => {
+                let node = self.path.node();
+                self.parent()
+                    .children()
+                    .into_iter()
+                    .filter(|c| c.path.node() != node)
+                    .collect()
+            }
+        }
+    }
+
     pub fn set_data(&self, data: D) -> Self {
         Self {
             path: self.path.set_data(data),
         }
     }
 
+    ///
+    /// returns true if the current subtrees of both paths hold the same data, compared recursively
+    /// (children being compared regardless of their order), whether or not they share nodes.
+    /// Unlike `==`, which compares the node identities of the paths, the ancestors are not considered
+    ///
+    pub fn structurally_eq(&self, other: &Path<D>) -> bool
+    where
+        D: PartialEq,
+    {
+        self.path.node().structurally_eq(&other.path.node())
+    }
+
+    ///
+    /// inserts the node `sub` points at (with its whole subtree) as a new child of the current node
+    /// and returns the path to the newly added child. `sub` can belong to another tree
+    ///
+    pub fn add_child_subtree(&self, sub: &Path<D>) -> Self {
+        let node = self.path.node();
+        let child = sub.path.node().copy();
+        let new_node = Node::new(node.data().clone(), node.0.children.insert(child.clone()));
+        Self {
+            path: self.path.propagate_last_node_change(new_node),
+        }
+        .child(child)
+    }
+
+    ///
+    /// replaces the current node (and its subtree) with the node (and subtree) `other` points at,
+    /// keeping its position under its parent. `other` can belong to another tree. When the current
+    /// node is the root, the returned path is the root of a new tree holding the subtree of `other`
+    ///
+    pub fn replace_subtree(&self, other: &Path<D>) -> Self {
+        let node = other.path.node().copy();
+        match self.path.node_vec.len() {
+            1 => Self {
+                path: Arc::new(PathPriv {
+                    node_vec: vec![node],
+                }),
+            },
+            _ => Self {
+                path: self.path.propagate_last_node_change(node),
+            },
+        }
+    }
+
+    ///
+    /// returns the root of a new tree holding a copy of the current node and its whole subtree,
+    /// detached from the ancestry of the current node (unlike `root()`, which goes up to the existing root)
+    ///
+    pub fn clone_as_root(&self) -> Self {
+        Self {
+            path: Arc::new(PathPriv {
+                node_vec: vec![self.path.node().copy()],
+            }),
+        }
+    }
+
+    ///
+    /// sets the data of the current node (keeping its children) only if it's equal to `expected`:
+    /// returns the updated path, or `Err` with the unchanged path on mismatch
+    ///
+    pub fn compare_and_set(&self, expected: &D, new: D) -> Result<Self, Self>
+    where
+        D: PartialEq,
+    {
+        match self.data() == expected {
+            true => Ok(self.apply(|_| Some(new))),
+            false => Err(self.clone()),
+        }
+    }
+
     pub fn apply<F: FnOnce(&D) -> Option<D>>(&self, f: F) -> Self {
         match self.path.apply(f) {
             Some(path) => Self { path },
@@ -393,6 +796,52 @@ impl<D: Clone> Path<D> {
         }
     }
 
+    ///
+    /// applies `f` to the data of each direct child (not recursively), keeping their subtrees.
+    /// Children for which `f` returns None are left unchanged
+    ///
+    pub fn map_children<F: FnMut(&D) -> Option<D>>(&self, mut f: F) -> Self {
+        match self.path.node().map_children(&mut f) {
+            Some(n) => Self {
+                path: self.path.propagate_last_node_change(n),
+            },
+            None => self.clone(),
+        }
+    }
+
+    ///
+    /// applies `f` to the data of the ancestor at the given depth (0 being the root, `depth()` the
+    /// current node), keeping its children. The focus stays on the current node: the ancestors above
+    /// the changed one are rebuilt, the nodes below are shared. Out of range depths are ignored
+    ///
+    pub fn apply_at_depth<F: FnOnce(&D) -> Option<D>>(&self, depth: usize, f: F) -> Self {
+        let node_vec = &self.path.node_vec;
+        if depth >= node_vec.len() {
+            return self.clone();
+        }
+
+        let prefix = PathPriv {
+            node_vec: node_vec[..=depth].to_vec(),
+        };
+        match prefix.apply(f) {
+            Some(p) => {
+                let mut new_path = p.node_vec.clone();
+                new_path.extend_from_slice(&node_vec[depth + 1..]);
+                Self {
+                    path: Arc::new(PathPriv { node_vec: new_path }),
+                }
+            }
+            None => self.clone(),
+        }
+    }
+
+    ///
+    /// applies `f` to the data of the root, the focus staying on the current node
+    ///
+    pub fn apply_to_root<F: FnOnce(&D) -> Option<D>>(&self, f: F) -> Self {
+        self.apply_at_depth(0, f)
+    }
+
     pub fn apply_recursive<F: FnMut(&D) -> Option<D>>(&self, mut f: F) -> Self {
         match self.path.apply_recursive(&mut f) {
             Some(path) => Self { path },
@@ -421,22 +870,34 @@ impl<D: Clone> Path<D> {
 
     pub fn flatten(&self) -> Vec<Self> {
         let mut res = Vec::new();
-        Self::flatten_recursive(&self, &mut res);
+        Self::flatten_recursive(self, &mut res);
         res
     }
 
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         self.path.node_vec.len()
     }
 
+    ///
+    /// returns the depth of the current node: the root has a depth of 0
+    ///
+    pub fn depth(&self) -> usize {
+        self.path.node_vec.len() - 1
+    }
+
+    ///
+    /// returns an iterator over the data of the current node, then its parent, up to the root
+    ///
+    pub fn ancestors(&self) -> impl Iterator<Item = &D> {
+        self.path.node_vec.iter().rev().map(|n| n.data())
+    }
+
     pub fn filter_recursive<F: Fn(&D) -> bool>(&self, f: F) -> Option<Self> {
-        match self.path.filter_recursive(f) {
-            Some(path) => Some(Self { path }),
-            None => None,
-        }
+        self.path.filter_recursive(f).map(|path| Self { path })
     }
 
-    // breath first
+    // depth first, pre-order
     pub fn iter_acc_recursive<Acc: TreeAcc<D>, F: FnMut(&mut Acc, &Path<D>)>(
         &self,
         init: &mut Acc,
@@ -444,7 +905,7 @@ impl<D: Clone> Path<D> {
     ) {
         init.push(self.data());
 
-        f(init, &self);
+        f(init, self);
         for c in self.children().iter() {
             c.iter_acc_recursive(init, f);
         }
@@ -452,7 +913,7 @@ impl<D: Clone> Path<D> {
         init.pop();
     }
 
-    // breath first
+    // depth first, pre-order
     #[inline(never)]
     pub fn iter_recursive<F: FnMut(&Path<D>)>(&self, f: &mut F) {
         f(self);
@@ -461,6 +922,51 @@ impl<D: Clone> Path<D> {
         }
     }
 
+    ///
+    /// depth first, pre-order walk of the current subtree, calling `f` with the depth of each node
+    /// relative to the current one (0 for the current node) and its data. The children are visited
+    /// in the order given by `children()`
+    ///
+    pub fn iter_with_depth<F: FnMut(usize, &D)>(&self, f: &mut F) {
+        let mut stack = vec![(0, self.path.node())];
+        while let Some((depth, n)) = stack.pop() {
+            f(depth, n.data());
+            let first = stack.len();
+            stack.extend(n.iter_children().map(|c| (depth + 1, c)));
+            stack[first..].reverse();
+        }
+    }
+
+    ///
+    /// breadth first walk of the current subtree: the nodes are visited level by level, a level
+    /// being complete before the next one starts. The children of a node are visited in the order
+    /// given by `children()`
+    ///
+    pub fn iter_bfs<F: FnMut(&Path<D>)>(&self, mut f: F) {
+        let mut queue = Queue::singleton(self.clone());
+        while let Some((p, rest)) = queue.try_dequeue() {
+            f(&p);
+            queue = rest.enqueue_all(p.children());
+        }
+    }
+
+    ///
+    /// removes the direct children (with their subtrees) for which `f` returns true. The other children
+    /// keep their subtrees. Removing all the children leaves an empty child set, as `remove_all_children` does
+    ///
+    pub fn remove_children<F: Fn(&D) -> bool>(&self, f: F) -> Self {
+        let node = self.path.node();
+        let children = node.0.children.filter(|c| !f(c.data()));
+        match children.len() == node.0.children.len() {
+            true => self.clone(),
+            false => Self {
+                path: self
+                    .path
+                    .propagate_last_node_change(Node::new(node.data().clone(), children)),
+            },
+        }
+    }
+
     pub fn remove_all_children(&self) -> Self {
         match self.path.node().0.children.len() {
             x if x > 0 => {
@@ -473,49 +979,322 @@ impl<D: Clone> Path<D> {
         }
     }
 
-    pub fn map_data<F: FnMut(&D) -> Option<D>>(&self, mut f: F) -> Self {
-        match self.path.node_vec[self.path.node_vec.len() - 1].map_data(&mut f) {
-            Some(n) => Path {
-                path: self.path.propagate_last_node_change(n),
-            },
-            None => self.clone(),
+    ///
+    /// returns a depth first, pre-order iterator over the current subtree: a node is visited
+    /// before its children, and the children are visited in the order given by `children()`
+    ///
+    pub fn iter_preorder(&self) -> PreorderIter<D> {
+        PreorderIter {
+            stack: vec![self.clone()],
         }
     }
-}
-
-impl<D: Clone> PartialEq for Path<D> {
-    fn eq(&self, other: &Self) -> bool {
-        if !Arc::ptr_eq(&self.path, &other.path) {
-            // check if the path length are different
-            if self.path.node_vec.len() != other.path.node_vec.len() {
-                return false;
-            }
-            // path is equal: check if node to node are equal
-            for i in 0..self.path.node_vec.len() {
-                if !Arc::ptr_eq(&self.path.node_vec[i].0, &other.path.node_vec[i].0) {
-                    return false;
-                }
-            }
 
-            println!("warning! slow path equality check");
-            // they are equal
-            true
-        } else {
-            true
+    ///
+    /// returns a depth first, post-order iterator over the current subtree: a node is visited
+    /// after its children, and the children are visited in the order given by `children()`
+    ///
+    pub fn iter_postorder(&self) -> PostorderIter<D> {
+        PostorderIter {
+            stack: vec![(self.clone(), false)],
         }
     }
-}
 
-impl<D: Clone> Eq for Path<D> {}
+    ///
+    /// returns the path (from the same root) to the first descendant of the current node whose data
+    /// satisfies `f`, searching in pre-order. The current node itself is not considered
+    ///
+    pub fn find_descendant<F: Fn(&D) -> bool>(&self, f: F) -> Option<Self> {
+        self.iter_preorder().skip(1).find(|p| f(p.data()))
+    }
 
-impl<D: Clone> Deref for Path<D> {
-    type Target = D;
-    fn deref(&self) -> &Self::Target {
-        self.data()
+    ///
+    /// returns a lazy pre-order iterator over the current subtree, yielding each path along with
+    /// a clone of its data
+    ///
+    pub fn iter_with_data(&self) -> impl Iterator<Item = (Path<D>, D)> {
+        self.iter_preorder().map(|p| {
+            let d = p.data().clone();
+            (p, d)
+        })
     }
-}
 
-impl<T: Clone> TreeAcc<T> for Vec<T> {
+    ///
+    /// returns the data of the current subtree, in depth first pre-order (same order as `iter_preorder`)
+    ///
+    pub fn to_vec(&self) -> Vec<D> {
+        self.path
+            .node()
+            .iter_preorder()
+            .map(|n| n.data().clone())
+            .collect()
+    }
+
+    ///
+    /// returns the number of leading nodes the two paths have in common (pointer identity),
+    /// 0 if they don't share the same root
+    ///
+    pub fn common_prefix_len(&self, other: &Path<D>) -> usize {
+        self.path
+            .node_vec
+            .iter()
+            .zip(other.path.node_vec.iter())
+            .take_while(|(a, b)| a == b)
+            .count()
+    }
+
+    ///
+    /// folds the data of the current subtree, in depth first pre-order (same order as `to_vec`)
+    ///
+    pub fn fold<B, F: FnMut(B, &D) -> B>(&self, init: B, mut f: F) -> B {
+        self.path
+            .node()
+            .iter_preorder()
+            .fold(init, |b, n| f(b, n.data()))
+    }
+
+    ///
+    /// returns the paths to the leaves (nodes without children) of the current subtree, in depth
+    /// first pre-order. A leaf returns itself
+    ///
+    pub fn leaves(&self) -> Vec<Self> {
+        self.iter_preorder()
+            .filter(|p| p.path.node().0.children.is_empty())
+            .collect()
+    }
+
+    ///
+    /// returns the number of nodes of the current subtree (the current node included)
+    ///
+    pub fn count(&self) -> usize {
+        self.path.node().iter_preorder().count()
+    }
+
+    ///
+    /// returns the height of the current subtree, counted in nodes: a leaf has a height of 1
+    ///
+    pub fn height(&self) -> usize {
+        self.depth_profile().len()
+    }
+
+    ///
+    /// returns the number of nodes found at each depth of the current subtree
+    /// (index 0 being the current node itself)
+    ///
+    pub fn depth_profile(&self) -> Vec<usize> {
+        let mut res = Vec::new();
+        let mut level = vec![self.path.node()];
+        while !level.is_empty() {
+            res.push(level.len());
+            level = level.iter().flat_map(|n| n.iter_children()).collect();
+        }
+        res
+    }
+
+    ///
+    /// removes every descendant for which `f` returns true, along with its subtree. Unlike
+    /// `filter_recursive` (which drops the current node itself when `f` is false for it), the
+    /// current node is always kept, so a valid path pointing at it is returned
+    ///
+    pub fn prune<F: Fn(&D) -> bool>(&self, f: F) -> Self {
+        match self.path.node().prune(&f) {
+            Some(n) => Self {
+                path: self.path.propagate_last_node_change(n),
+            },
+            None => self.clone(),
+        }
+    }
+
+    ///
+    /// drops the descendants deeper than `max_depth` levels below the current node (0 keeps the
+    /// current node alone). The current node keeps its position in the tree; use `clone_as_root`
+    /// to detach the truncated subtree
+    ///
+    pub fn truncate_depth(&self, max_depth: usize) -> Self {
+        match self.path.node().truncate_depth(max_depth) {
+            Some(n) => Self {
+                path: self.path.propagate_last_node_change(n),
+            },
+            None => self.clone(),
+        }
+    }
+
+    ///
+    /// returns the whole tree (from the root) with the data of every node mapped through `f`,
+    /// the returned path pointing at the node matching the current one
+    ///
+    pub fn map_tree<W: Clone, F: FnMut(&D) -> W>(&self, mut f: F) -> Path<W> {
+        let node_vec = &self.path.node_vec;
+        let mut mapped = vec![None; node_vec.len()];
+        node_vec[0].map_tree(&mut f, node_vec, &mut mapped);
+        Path {
+            path: Arc::new(PathPriv {
+                node_vec: mapped.into_iter().map(|n| n.unwrap()).collect(),
+            }),
+        }
+    }
+
+    pub fn map_data<F: FnMut(&D) -> Option<D>>(&self, mut f: F) -> Self {
+        match self.path.node_vec[self.path.node_vec.len() - 1].map_data(&mut f) {
+            Some(n) => Path {
+                path: self.path.propagate_last_node_change(n),
+            },
+            None => self.clone(),
+        }
+    }
+}
+
+impl<D: Clone + Ord> Path<D> {
+    ///
+    /// returns the children sorted by their data. Siblings with equal data are ordered by the height
+    /// of their subtree, then by a canonical rank of the subtree, computed once bottom-up over the
+    /// subtree of the current node: O(n log(n)). The order doesn't depend on how the tree was
+    /// built: only identical subtrees may come in any order
+    ///
+    pub fn children_sorted(&self) -> Vec<Self> {
+        let node = self.path.node();
+        node.sorted_children(&node.canonical_ranks())
+            .into_iter()
+            .map(|c| self.child(c))
+            .collect()
+    }
+
+    ///
+    /// returns the path to the i-th child in the order of `children_sorted()`, None if out of bounds
+    ///
+    pub fn child_at(&self, i: usize) -> Option<Self> {
+        self.children_sorted().into_iter().nth(i)
+    }
+
+    ///
+    /// returns the index of each node of the path among its siblings (in the order of
+    /// `children_sorted()`), from the first child below the root down to the current node.
+    /// The root has an empty index path
+    ///
+    pub fn index_path(&self) -> Vec<usize> {
+        let ranks = self.path.node_vec[0].canonical_ranks();
+        self.path
+            .node_vec
+            .windows(2)
+            .map(|w| {
+                let children = w[0].sorted_children(&ranks);
+                children.iter().position(|c| *c == w[1]).unwrap()
+            })
+            .collect()
+    }
+
+    ///
+    /// returns the path obtained by following `indices` (as returned by `index_path`) down from the
+    /// current node of `root`, None if an index is out of bounds
+    ///
+    pub fn navigate(root: &Path<D>, indices: &[usize]) -> Option<Self> {
+        let ranks = root.path.node().canonical_ranks();
+        indices.iter().try_fold(root.clone(), |p, i| {
+            let c = p.path.node().sorted_children(&ranks).into_iter().nth(*i)?;
+            Some(p.child(c))
+        })
+    }
+
+    fn sibling_at(&self, offset: isize) -> Option<Self> {
+        match self.len() {
+            1 => None,
+            _ => {
+                let node = self.path.node();
+                let siblings = self.parent().children_sorted();
+                let idx = siblings.iter().position(|c| c.path.node() == node)?;
+                let idx = idx as isize + offset;
+                match idx >= 0 && (idx as usize) < siblings.len() {
+                    true => Some(siblings[idx as usize].clone()),
+                    false => None,
+                }
+            }
+        }
+    }
+
+    ///
+    /// returns the next sibling, siblings being ordered by their data. None at the root or for the last sibling
+    ///
+    pub fn next_sibling(&self) -> Option<Self> {
+        self.sibling_at(1)
+    }
+
+    ///
+    /// returns the previous sibling, siblings being ordered by their data. None at the root or for the first sibling
+    ///
+    pub fn prev_sibling(&self) -> Option<Self> {
+        self.sibling_at(-1)
+    }
+}
+
+#[derive(Clone)]
+pub struct PreorderIter<D: Clone> {
+    stack: Vec<Path<D>>,
+}
+
+impl<D: Clone> std::iter::Iterator for PreorderIter<D> {
+    type Item = Path<D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let p = self.stack.pop()?;
+        self.stack.extend(p.children().into_iter().rev());
+        Some(p)
+    }
+}
+
+#[derive(Clone)]
+pub struct PostorderIter<D: Clone> {
+    // each path is paired with whether its children were already pushed
+    stack: Vec<(Path<D>, bool)>,
+}
+
+impl<D: Clone> std::iter::Iterator for PostorderIter<D> {
+    type Item = Path<D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (p, expanded) = self.stack.pop()?;
+            if expanded {
+                return Some(p);
+            }
+            let children = p.children();
+            self.stack.push((p, true));
+            self.stack
+                .extend(children.into_iter().rev().map(|c| (c, false)));
+        }
+    }
+}
+
+impl<D: Clone> PartialEq for Path<D> {
+    fn eq(&self, other: &Self) -> bool {
+        if !Arc::ptr_eq(&self.path, &other.path) {
+            // check if the path length are different
+            if self.path.node_vec.len() != other.path.node_vec.len() {
+                return false;
+            }
+            // path is equal: check if node to node are equal
+            for i in 0..self.path.node_vec.len() {
+                if !Arc::ptr_eq(&self.path.node_vec[i].0, &other.path.node_vec[i].0) {
+                    return false;
+                }
+            }
+
+            // they are equal
+            true
+        } else {
+            true
+        }
+    }
+}
+
+impl<D: Clone> Eq for Path<D> {}
+
+impl<D: Clone> Deref for Path<D> {
+    type Target = D;
+    fn deref(&self) -> &Self::Target {
+        self.data()
+    }
+}
+
+impl<T: Clone> TreeAcc<T> for Vec<T> {
     fn push(&mut self, data: &T) {
         self.push(data.clone());
     }
@@ -525,6 +1304,76 @@ impl<T: Clone> TreeAcc<T> for Vec<T> {
     }
 }
 
+// serialization: the current node and its subtree are written as a recursive
+// `{data, children: [...]}` structure, children being in the order of `children_sorted()`
+#[cfg(feature = "serde")]
+struct SerNode<'a, D: Clone>(Node<D>, &'a Ranks<D>);
+
+#[cfg(feature = "serde")]
+impl<D: Clone + Ord + serde::Serialize> serde::Serialize for SerNode<'_, D> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let children = self
+            .0
+            .sorted_children(self.1)
+            .into_iter()
+            .map(|c| SerNode(c, self.1))
+            .collect::<Vec<_>>();
+
+        let mut s = serializer.serialize_struct("Node", 2)?;
+        s.serialize_field("data", self.0.data())?;
+        s.serialize_field("children", &children)?;
+        s.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "Node")]
+struct DeNode<D> {
+    data: D,
+    children: Vec<DeNode<D>>,
+}
+
+#[cfg(feature = "serde")]
+impl<D: Clone> DeNode<D> {
+    fn into_node(self) -> Node<D> {
+        let children = self
+            .children
+            .into_iter()
+            .fold(HashSet::empty(), |s, c| s.insert(c.into_node()));
+        Node::new(self.data, children)
+    }
+}
+
+///
+/// serializes the current node and its subtree (not its ancestors), children being sorted by
+/// their data for a deterministic output
+///
+#[cfg(feature = "serde")]
+impl<D: Clone + Ord + serde::Serialize> serde::Serialize for Path<D> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let node = self.path.node();
+        SerNode(node.clone(), &node.canonical_ranks()).serialize(serializer)
+    }
+}
+
+///
+/// deserializes a tree and returns the path to its root
+///
+#[cfg(feature = "serde")]
+impl<'de, D: Clone + serde::Deserialize<'de>> serde::Deserialize<'de> for Path<D> {
+    fn deserialize<De: serde::Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let node = DeNode::deserialize(deserializer)?.into_node();
+        Ok(Self {
+            path: Arc::new(PathPriv {
+                node_vec: vec![node],
+            }),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::tree::*;
@@ -591,6 +1440,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::iter_next_slice)]
     fn remove_roots() {
         let mut tree = Path::new(0);
         for i in 0..128 {
@@ -826,4 +1676,1166 @@ mod tests {
         let nn8 = n8.map_data(|_| None);
         assert!(nn8 == n8);
     }
+
+    #[test]
+    fn test_depth_profile() {
+        let mut tree = Path::new(0);
+        tree = tree.add_node(1).add_node(10).add_node(100).root();
+        tree = tree.children()[0].children()[0]
+            .parent()
+            .add_node(11)
+            .root();
+        tree = tree.add_node(2).root().add_node(3).root();
+
+        assert_eq!(tree.depth_profile(), vec![1, 3, 2, 1]);
+
+        let n1 = tree
+            .children()
+            .into_iter()
+            .find(|c| *c.data() == 1)
+            .unwrap();
+        assert_eq!(n1.depth_profile(), vec![1, 2, 1]);
+
+        let n3 = tree
+            .children()
+            .into_iter()
+            .find(|c| *c.data() == 3)
+            .unwrap();
+        assert_eq!(n3.depth_profile(), vec![1]);
+    }
+
+    #[test]
+    fn test_common_prefix_len() {
+        let mut tree = Path::new(0);
+        tree = tree.add_node(1).add_node(2).add_node(3).add_node(4).root();
+        tree = tree.children()[0].children()[0]
+            .add_node(5)
+            .add_node(6)
+            .root();
+
+        let n2 = tree.children()[0].children()[0].clone();
+        assert_eq!(*n2.data(), 2);
+        let mut a = n2.children().into_iter().find(|c| *c.data() == 3).unwrap();
+        a = a.children()[0].clone();
+        let mut b = n2.children().into_iter().find(|c| *c.data() == 5).unwrap();
+        b = b.children()[0].clone();
+
+        assert_eq!(a.len(), 5);
+        assert_eq!(b.len(), 5);
+        assert_eq!(a.common_prefix_len(&b), 3);
+        assert_eq!(b.common_prefix_len(&a), 3);
+        assert_eq!(a.common_prefix_len(&a), 5);
+        assert_eq!(a.common_prefix_len(&n2), 3);
+
+        // a different tree doesn't share the root
+        let other = Path::new(0).add_node(1);
+        assert_eq!(a.common_prefix_len(&other), 0);
+
+        // editing the tree creates a new root
+        let edited = a.set_data(40);
+        assert_eq!(edited.common_prefix_len(&b), 0);
+    }
+
+    #[test]
+    fn test_iter_preorder_postorder() {
+        //      0
+        //     / \
+        //    1   4
+        //    |   |
+        //    2   5
+        //    |
+        //    3
+        let mut tree = Path::new(0);
+        tree = tree.add_node(1).add_node(2).add_node(3).root();
+        tree = tree.add_node(4).add_node(5).root();
+
+        // the order of the root's children follows `children()`
+        let first = *tree.children()[0].data();
+        let branch = |first: i32| match first {
+            1 => vec![1, 2, 3],
+            _ => vec![4, 5],
+        };
+        let (a, b) = (branch(first), branch(5 - first));
+
+        let pre = tree.iter_preorder().map(|p| *p.data()).collect::<Vec<_>>();
+        let mut expected = vec![0];
+        expected.extend(a.iter());
+        expected.extend(b.iter());
+        assert_eq!(pre, expected);
+
+        let post = tree.iter_postorder().map(|p| *p.data()).collect::<Vec<_>>();
+        let mut expected = Vec::new();
+        expected.extend(a.iter().rev());
+        expected.extend(b.iter().rev());
+        expected.push(0);
+        assert_eq!(post, expected);
+
+        // the paths keep their ancestry
+        let depth = [1, 2, 3, 4, 2, 3];
+        for p in tree.iter_postorder() {
+            assert_eq!(p.len(), depth[*p.data() as usize]);
+            assert_eq!(*p.root().data(), 0);
+        }
+
+        let leaf = Path::new(7);
+        assert_eq!(leaf.iter_preorder().count(), 1);
+        assert_eq!(leaf.iter_postorder().count(), 1);
+    }
+
+    #[test]
+    fn test_to_vec() {
+        let mut tree = Path::new(0);
+        tree = tree.add_node(1).add_node(2).add_node(3).root();
+        tree = tree.add_node(4).add_node(5).root();
+        tree = tree.add_node(6).root();
+
+        let v = tree.to_vec();
+        assert_eq!(v.len(), tree.flatten().len());
+        assert_eq!(v.len(), 7);
+        assert_eq!(
+            v,
+            tree.iter_preorder().map(|p| *p.data()).collect::<Vec<_>>()
+        );
+        assert_eq!(v[0], 0);
+
+        let n1 = tree
+            .children()
+            .into_iter()
+            .find(|c| *c.data() == 1)
+            .unwrap();
+        assert_eq!(n1.to_vec(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_count_height() {
+        //      0
+        //    / | \
+        //   1  4  6
+        //   |  |
+        //   2  5
+        //   |
+        //   3
+        let mut tree = Path::new(0);
+        tree = tree.add_node(1).add_node(2).add_node(3).root();
+        tree = tree.add_node(4).add_node(5).root();
+        tree = tree.add_node(6).root();
+
+        assert_eq!(tree.count(), 7);
+        assert_eq!(tree.height(), 4);
+
+        let n4 = tree
+            .children()
+            .into_iter()
+            .find(|c| *c.data() == 4)
+            .unwrap();
+        assert_eq!(n4.count(), 2);
+        assert_eq!(n4.height(), 2);
+
+        let n6 = tree
+            .children()
+            .into_iter()
+            .find(|c| *c.data() == 6)
+            .unwrap();
+        assert_eq!(n6.count(), 1);
+        assert_eq!(n6.height(), 1);
+    }
+
+    #[test]
+    fn test_find_child() {
+        let mut tree = Path::new(0);
+        for i in 1..10 {
+            tree = tree.add_node(i).add_node(i * 10).root();
+        }
+
+        let n5 = tree.find_child(|d| *d == 5).unwrap();
+        assert_eq!(*n5.data(), 5);
+        assert_eq!(n5.len(), 2);
+        assert_eq!(*n5.parent().data(), 0);
+
+        let n50 = n5.find_child(|d| *d == 50).unwrap();
+        assert_eq!(*n50.data(), 50);
+        assert_eq!(*n50.root().data(), 0);
+
+        // the found path can be edited
+        let edited = n50.add_node(500).root();
+        assert_eq!(edited.count(), tree.count() + 1);
+
+        assert!(tree.find_child(|d| *d == 50).is_none());
+        assert!(n50.find_child(|_| true).is_none());
+    }
+
+    #[test]
+    fn test_iter_with_data() {
+        let mut tree = Path::new(0);
+        for i in 1..5 {
+            tree = tree.add_node(i).add_node(i * 10).root();
+        }
+
+        let v = tree.iter_with_data().collect::<Vec<_>>();
+        assert_eq!(v.len(), tree.count());
+        for (p, d) in v.iter() {
+            assert_eq!(*p.data(), *d);
+        }
+        let mut data = v.iter().map(|(_, d)| *d).collect::<Vec<_>>();
+        data.sort();
+        assert_eq!(data, vec![0, 1, 2, 3, 4, 10, 20, 30, 40]);
+
+        let big = tree
+            .iter_with_data()
+            .filter(|(_, d)| *d >= 10)
+            .map(|(p, _)| p)
+            .collect::<Vec<_>>();
+        assert_eq!(big.len(), 4);
+        for p in big {
+            assert_eq!(*p.data(), *p.parent().data() * 10);
+        }
+    }
+
+    #[test]
+    fn test_find_descendant() {
+        let mut tree = Path::new(0);
+        for i in 1..5 {
+            tree = tree.add_node(i).add_node(i * 10).add_node(i * 100).root();
+        }
+
+        let n300 = tree.find_descendant(|d| *d == 300).unwrap();
+        assert_eq!(*n300.data(), 300);
+        assert_eq!(n300.len(), 4);
+        assert_eq!(*n300.parent().data(), 30);
+        assert_eq!(*n300.parent().parent().data(), 3);
+        assert_eq!(*n300.root().data(), 0);
+
+        let edited = n300.parent().set_data(31).root();
+        assert!(edited.find_descendant(|d| *d == 31).is_some());
+        assert!(edited.find_descendant(|d| *d == 30).is_none());
+
+        let n2 = tree.find_child(|d| *d == 2).unwrap();
+        assert!(n2.find_descendant(|d| *d == 300).is_none());
+        assert!(n2.find_descendant(|d| *d == 2).is_none());
+        assert_eq!(*n2.find_descendant(|d| *d > 2).unwrap().data(), 20);
+    }
+
+    #[test]
+    fn test_siblings() {
+        let mut tree = Path::new(0);
+        for i in [3, 1, 4, 2, 5] {
+            tree = tree.add_node(i).add_node(i * 10).root();
+        }
+
+        let n3 = tree.find_child(|d| *d == 3).unwrap();
+        let siblings = n3.siblings();
+        assert_eq!(siblings.len(), 4);
+        let mut data = siblings.iter().map(|s| *s.data()).collect::<Vec<_>>();
+        data.sort();
+        assert_eq!(data, vec![1, 2, 4, 5]);
+        for s in siblings.iter() {
+            assert_eq!(*s.parent().data(), 0);
+        }
+
+        assert_eq!(*n3.next_sibling().unwrap().data(), 4);
+        assert_eq!(*n3.prev_sibling().unwrap().data(), 2);
+
+        let n1 = tree.find_child(|d| *d == 1).unwrap();
+        assert!(n1.prev_sibling().is_none());
+        let mut order = vec![*n1.data()];
+        let mut n = n1;
+        while let Some(next) = n.next_sibling() {
+            order.push(*next.data());
+            n = next;
+        }
+        assert_eq!(order, vec![1, 2, 3, 4, 5]);
+
+        // an only child has no siblings
+        let n30 = n3.children()[0].clone();
+        assert!(n30.siblings().is_empty());
+        assert!(n30.next_sibling().is_none());
+
+        assert!(tree.siblings().is_empty());
+        assert!(tree.next_sibling().is_none());
+        assert!(tree.prev_sibling().is_none());
+    }
+
+    #[test]
+    fn test_map_tree() {
+        let mut tree = Path::new(0);
+        for i in 1..5 {
+            tree = tree.add_node(i).add_node(i * 10).add_node(i * 100).root();
+        }
+
+        let n200 = tree.find_descendant(|d| *d == 200).unwrap();
+        let mapped = n200.map_tree(|d| format!("#{}", d));
+
+        assert_eq!(*mapped.data(), "#200");
+        assert_eq!(mapped.len(), n200.len());
+        assert_eq!(*mapped.parent().data(), "#20");
+        assert_eq!(*mapped.root().data(), "#0");
+        assert_eq!(mapped.root().flatten().len(), tree.flatten().len());
+        assert_eq!(mapped.root().depth_profile(), tree.depth_profile());
+
+        let mut v = mapped.root().to_vec();
+        v.sort();
+        let mut expected = tree
+            .to_vec()
+            .iter()
+            .map(|d| format!("#{}", d))
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(v, expected);
+
+        // the new tree can be edited as usual
+        let edited = mapped.add_node("#2000".to_string()).root();
+        assert_eq!(edited.count(), tree.count() + 1);
+    }
+
+    #[test]
+    fn test_fold() {
+        let mut tree = Path::new(0);
+        for i in 1..5 {
+            tree = tree.add_node(i).add_node(i * 10).add_node(i * 100).root();
+        }
+
+        let sum = tree.fold(0, |acc, d| acc + d);
+        assert_eq!(sum, tree.to_vec().iter().sum::<i32>());
+        assert_eq!(sum, 1110);
+
+        let labels = tree.fold(String::new(), |acc, d| format!("{}{},", acc, d));
+        let expected = tree
+            .to_vec()
+            .iter()
+            .fold(String::new(), |acc, d| format!("{}{},", acc, d));
+        assert_eq!(labels, expected);
+
+        let n3 = tree.find_child(|d| *d == 3).unwrap();
+        assert_eq!(n3.fold(0, |acc, d| acc + d), 333);
+    }
+
+    #[test]
+    fn test_compare_and_set() {
+        let tree = Path::new(0).add_node(1).add_node(2).parent();
+        assert_eq!(*tree.data(), 1);
+
+        let updated = tree.compare_and_set(&1, 10).ok().unwrap();
+        assert_eq!(*updated.data(), 10);
+        assert_eq!(updated.children().len(), 1);
+        assert_eq!(*updated.children()[0].data(), 2);
+        assert_eq!(*updated.root().children()[0].data(), 10);
+
+        let failed = updated.compare_and_set(&1, 20).err().unwrap();
+        assert!(failed == updated);
+        assert_eq!(*failed.data(), 10);
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut tree = Path::new(0);
+        for i in 1..5 {
+            tree = tree.add_node(i).add_node(i * 10).add_node(i * 100).root();
+        }
+        tree = tree.find_child(|d| *d == 2).unwrap().add_node(21).root();
+        assert_eq!(tree.count(), 14);
+
+        // remove the subtree of 2 (2, 20, 200, 21) and the leaf 300
+        let pruned = tree.prune(|d| *d == 2 || *d == 300);
+        assert_eq!(pruned.count(), 9);
+        let mut v = pruned.to_vec();
+        v.sort();
+        assert_eq!(v, vec![0, 1, 3, 4, 10, 30, 40, 100, 400]);
+
+        // the current node is kept, even if it matches
+        let n1 = tree.find_child(|d| *d == 1).unwrap();
+        let pruned = n1.prune(|d| *d < 100);
+        assert_eq!(*pruned.data(), 1);
+        assert_eq!(pruned.count(), 1);
+        assert_eq!(pruned.root().count(), 12);
+        assert!(n1.filter_recursive(|d| *d >= 100).is_none());
+
+        // nothing to remove
+        let same = tree.prune(|d| *d > 1000);
+        assert!(same == tree);
+    }
+
+    #[test]
+    fn test_ancestors() {
+        let mut tree = Path::new(0);
+        assert_eq!(tree.depth(), 0);
+        assert_eq!(tree.ancestors().cloned().collect::<Vec<_>>(), vec![0]);
+
+        for i in 1..20 {
+            tree = tree.add_node(i);
+        }
+        assert_eq!(tree.depth(), 19);
+        assert_eq!(
+            tree.ancestors().cloned().collect::<Vec<_>>(),
+            (0..20).rev().collect::<Vec<_>>()
+        );
+
+        let p = tree.parent().parent();
+        assert_eq!(p.depth(), 17);
+        assert_eq!(p.ancestors().next(), Some(&17));
+        assert_eq!(p.ancestors().last(), Some(&0));
+        assert_eq!(tree.root().ancestors().count(), 1);
+    }
+
+    #[test]
+    fn test_replace_subtree() {
+        let mut tree = Path::new(0);
+        for i in 1..4 {
+            tree = tree.add_node(i).add_node(i * 10).root();
+        }
+
+        // 3 node subtree: 100 -> (101, 102)
+        let sub = Path::new(100).add_node(101).parent().add_node(102).parent();
+        assert_eq!(sub.count(), 3);
+
+        let n2 = tree.find_child(|d| *d == 2).unwrap();
+        let grafted = n2.replace_subtree(&sub);
+        assert_eq!(*grafted.data(), 100);
+        assert_eq!(grafted.depth(), 1);
+        assert_eq!(grafted.children().len(), 2);
+        let mut v = grafted.to_vec();
+        v.sort();
+        assert_eq!(v, vec![100, 101, 102]);
+
+        let root = grafted.root();
+        assert_eq!(root.children().len(), 3);
+        assert_eq!(root.count(), 8);
+        assert!(root.find_descendant(|d| *d == 2 || *d == 20).is_none());
+        // the original tree is untouched
+        assert_eq!(tree.count(), 7);
+
+        // grafting a copy of a sibling keeps both
+        let n1 = root.find_child(|d| *d == 1).unwrap();
+        let n3 = root.find_child(|d| *d == 3).unwrap();
+        let root = n3.replace_subtree(&n1).root();
+        assert_eq!(root.children().len(), 3);
+        assert_eq!(root.count(), 8);
+
+        // replacing the root
+        let new_root = tree.replace_subtree(&sub.find_child(|d| *d == 101).unwrap());
+        assert_eq!(new_root.depth(), 0);
+        assert_eq!(*new_root.data(), 101);
+        assert_eq!(new_root.count(), 1);
+    }
+
+    #[test]
+    fn test_add_child_subtree() {
+        let tree = Path::new(0).add_node(1).add_node(10).root();
+        let sub = Path::new(100)
+            .add_node(101)
+            .add_node(1010)
+            .root()
+            .add_node(102)
+            .root();
+
+        let n1 = tree.find_child(|d| *d == 1).unwrap();
+        let added = n1.add_child_subtree(&sub);
+        assert_eq!(*added.data(), 100);
+        assert_eq!(added.depth(), 2);
+        assert_eq!(added.count(), 4);
+        assert_eq!(*added.parent().data(), 1);
+
+        let children = added.parent().children();
+        assert_eq!(children.len(), 2);
+        let c = children.iter().find(|c| *c.data() == 100).unwrap();
+        let mut v = c.to_vec();
+        v.sort();
+        assert_eq!(v, vec![100, 101, 102, 1010]);
+        assert_eq!(added.root().count(), 7);
+
+        // attaching the same subtree twice keeps both copies
+        let twice = added.parent().add_child_subtree(&sub);
+        assert_eq!(twice.parent().children().len(), 3);
+        assert_eq!(twice.root().count(), 11);
+
+        // the original trees are untouched
+        assert_eq!(tree.count(), 3);
+        assert_eq!(sub.count(), 4);
+    }
+
+    #[test]
+    fn test_structurally_eq() {
+        fn build(order: &[i32]) -> Path<i32> {
+            let mut tree = Path::new(0);
+            for i in order {
+                tree = tree.add_node(*i).add_node(*i * 10).root();
+            }
+            tree.add_node(5).root()
+        }
+
+        let a = build(&[1, 2, 3, 4]);
+        let b = build(&[4, 3, 2, 1]);
+        assert!(a != b);
+        assert!(a.structurally_eq(&b));
+        assert!(a.structurally_eq(&a));
+
+        let a2 = a.find_child(|d| *d == 2).unwrap();
+        let b2 = b.find_child(|d| *d == 2).unwrap();
+        assert!(a2 != b2);
+        assert!(a2.structurally_eq(&b2));
+        assert!(!a2.structurally_eq(&a));
+
+        // different data deep in the tree
+        let c = b2.find_child(|d| *d == 20).unwrap().set_data(21).root();
+        assert!(!a.structurally_eq(&c));
+
+        // same data, different shape
+        let d = b.find_child(|d| *d == 5).unwrap().add_node(50).root();
+        assert!(!a.structurally_eq(&d));
+
+        // duplicated children are counted
+        let e = a.add_node(5).root();
+        let f = a.add_node(6).root().add_node(5).root();
+        assert!(!e.structurally_eq(&b));
+        assert!(!e.structurally_eq(&f));
+        assert!(e.structurally_eq(&b.add_node(5).root()));
+    }
+
+    #[test]
+    fn test_slow_path_eq() {
+        let tree = Path::new(0).add_node(1).add_node(2).root();
+
+        // distinct paths to the same nodes go through the node to node comparison
+        let a = tree.find_child(|d| *d == 1).unwrap();
+        let b = tree.find_child(|d| *d == 1).unwrap();
+        assert!(!Arc::ptr_eq(&a.path, &b.path));
+        assert!(a == b);
+        assert!(a.children()[0] == b.children()[0]);
+        assert!(a != a.add_node(3).parent());
+        assert!(a != tree);
+    }
+
+    #[test]
+    fn test_slow_path_eq_prints_nothing() {
+        // run test_slow_path_eq alone in a child process, without capturing its output
+        let out = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["tree::tests::test_slow_path_eq", "--exact", "--nocapture"])
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        assert!(out.status.success());
+        assert!(stdout.contains("1 passed"));
+        assert!(!stdout.contains("slow path equality"));
+    }
+
+    #[test]
+    fn test_child_at() {
+        fn build(order: &[i32]) -> Path<i32> {
+            let mut tree = Path::new(0);
+            for i in order {
+                tree = tree.add_node(*i % 3).add_node(*i).root();
+            }
+            tree
+        }
+
+        let expected = [(0, 3), (0, 6), (1, 1), (1, 4), (2, 2), (2, 5)];
+        for order in [[1, 2, 3, 4, 5, 6], [6, 5, 4, 3, 2, 1], [4, 1, 6, 2, 5, 3]] {
+            let tree = build(&order);
+            let children = tree.children_sorted();
+            assert_eq!(children.len(), 6);
+            for (i, (d, c)) in expected.iter().enumerate() {
+                let child = tree.child_at(i).unwrap();
+                assert!(child == children[i]);
+                assert_eq!(child.data(), d);
+                assert_eq!(child.child_at(0).unwrap().data(), c);
+            }
+            assert!(tree.child_at(6).is_none());
+        }
+        assert!(Path::new(0).child_at(0).is_none());
+    }
+
+    #[test]
+    fn test_child_at_deep_equal_data() {
+        // two chains of zeros, only told apart by their leaves
+        let chain = |leaf: i32| {
+            let mut node = Node::new(leaf, HashSet::empty());
+            for _ in 0..10000 {
+                node = Node::new(0, HashSet::empty().insert(node));
+            }
+            node
+        };
+        let tree = Path {
+            path: Arc::new(PathPriv {
+                node_vec: vec![Node::new(
+                    0,
+                    HashSet::empty().insert(chain(2)).insert(chain(1)),
+                )],
+            }),
+        };
+
+        let first = tree.child_at(0).unwrap();
+        let mut deepest = first.clone();
+        while let Some(c) = deepest.children().first() {
+            deepest = c.clone();
+        }
+        assert_eq!(*deepest.data(), 1);
+
+        let indices = deepest.index_path();
+        assert_eq!(indices.len(), 10001);
+        assert_eq!(indices[0], 0);
+        assert!(indices[1..].iter().all(|i| *i == 0));
+        assert!(Path::navigate(&tree, &indices).unwrap() == deepest);
+        assert!(first.next_sibling().unwrap() == tree.child_at(1).unwrap());
+    }
+
+    #[test]
+    fn test_child_at_wide_equal_data() {
+        // many children with the same data, told apart by their own children
+        let mut tree = Path::new(0);
+        for i in 0..2000 {
+            let leaf = (i * 7919) % 2000;
+            tree = tree.add_node(0).add_node(leaf).parent().add_node(-1).root();
+        }
+
+        let children = tree.children_sorted();
+        assert_eq!(children.len(), 2000);
+        for (i, c) in children.iter().enumerate() {
+            assert_eq!(*c.data(), 0);
+            let grandchildren = c.children_sorted();
+            assert_eq!(*grandchildren[0].data(), -1);
+            assert_eq!(*grandchildren[1].data(), i as i32);
+        }
+        assert!(tree.child_at(1999).unwrap() == children[1999]);
+        assert_eq!(
+            children[1234].children_sorted()[1].index_path(),
+            vec![1234, 1]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut tree = Path::new(String::from("root"));
+        for i in 0..5 {
+            let mut p = tree.add_node(format!("c{}", i));
+            for j in 0..i {
+                p = p.add_node(format!("c{}{}", i, j)).parent();
+            }
+            tree = p.root();
+        }
+
+        let json = serde_json::to_string(&tree).unwrap();
+        let reloaded: Path<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(reloaded.count(), tree.count());
+        assert_eq!(reloaded.count(), 16);
+        let (mut a, mut b) = (tree.to_vec(), reloaded.to_vec());
+        a.sort();
+        b.sort();
+        assert_eq!(a, b);
+        assert!(reloaded.structurally_eq(&tree));
+
+        // deterministic output
+        assert_eq!(serde_json::to_string(&reloaded).unwrap(), json);
+
+        // only the current subtree is serialized
+        let c3 = tree.find_child(|d| d == "c3").unwrap();
+        let reloaded: Path<String> =
+            serde_json::from_str(&serde_json::to_string(&c3).unwrap()).unwrap();
+        assert_eq!(reloaded.depth(), 0);
+        assert_eq!(reloaded.data(), "c3");
+        assert_eq!(reloaded.count(), 4);
+
+        let leaf: Path<String> = serde_json::from_str(r#"{"data":"leaf","children":[]}"#).unwrap();
+        assert_eq!(leaf.count(), 1);
+    }
+
+    #[test]
+    fn test_leaves() {
+        let tree = Path::new(0)
+            .add_node(1)
+            .add_node(10)
+            .parent()
+            .add_node(11)
+            .add_node(110)
+            .root()
+            .add_node(2)
+            .root()
+            .add_node(3)
+            .add_node(30)
+            .root();
+
+        let leaves = tree.leaves();
+        let mut v = leaves.iter().map(|p| *p.data()).collect::<Vec<_>>();
+        v.sort();
+        assert_eq!(v, vec![2, 10, 30, 110]);
+        for l in leaves.iter() {
+            assert!(l.children().is_empty());
+            assert_eq!(*l.root().data(), 0);
+        }
+        let l110 = leaves.iter().find(|p| *p.data() == 110).unwrap();
+        assert_eq!(
+            l110.ancestors().cloned().collect::<Vec<_>>(),
+            vec![110, 11, 1, 0]
+        );
+
+        let n1 = tree.find_child(|d| *d == 1).unwrap();
+        assert_eq!(n1.leaves().len(), 2);
+
+        let leaf = tree.find_child(|d| *d == 2).unwrap();
+        let leaves = leaf.leaves();
+        assert_eq!(leaves.len(), 1);
+        assert!(leaves[0] == leaf);
+        assert_eq!(Path::new(0).leaves().len(), 1);
+    }
+
+    #[test]
+    fn test_map_children() {
+        let mut tree = Path::new(1).add_node(1000).root();
+        for i in 1..5 {
+            tree = tree.add_node(i).add_node(i * 10).add_node(i * 100).root();
+        }
+        let n = tree.find_child(|d| *d == 1000).unwrap();
+
+        let doubled = n.parent().map_children(|d| Some(d * 2));
+        assert_eq!(doubled.count(), tree.count());
+        let mut v = doubled
+            .children()
+            .iter()
+            .map(|c| *c.data())
+            .collect::<Vec<_>>();
+        v.sort();
+        assert_eq!(v, vec![2, 4, 6, 8, 2000]);
+        assert_eq!(*doubled.data(), 1);
+        for i in 1..5 {
+            let c = doubled.find_child(|d| *d == i * 2).unwrap();
+            assert_eq!(c.to_vec(), vec![i * 2, i * 10, i * 100]);
+        }
+
+        // None leaves the child (and the path) unchanged
+        let same = tree.map_children(|_| None);
+        assert!(same == tree);
+        let odd = tree.map_children(|d| if *d == 3 { Some(33) } else { None });
+        assert!(odd.find_child(|d| *d == 33).is_some());
+        assert!(odd.find_child(|d| *d == 3).is_none());
+        assert_eq!(odd.children().len(), 5);
+
+        // on an inner node, the ancestry is rebuilt
+        let n2 = tree
+            .find_child(|d| *d == 2)
+            .unwrap()
+            .map_children(|d| Some(d + 1));
+        assert_eq!(n2.root().find_descendant(|d| *d == 21).unwrap().depth(), 2);
+        assert_eq!(n2.root().count(), tree.count());
+    }
+
+    // builds a complete tree of the given depth and fan-out, numbering the nodes from 0
+    fn complete_tree(depth: usize, fan_out: usize, next: &mut usize) -> Node<usize> {
+        let data = *next;
+        *next += 1;
+        let mut children = HashSet::empty();
+        if depth > 1 {
+            for _ in 0..fan_out {
+                children = children.insert(complete_tree(depth - 1, fan_out, next));
+            }
+        }
+        Node::new(data, children)
+    }
+
+    #[test]
+    fn test_apply_recursive_visits_once() {
+        let mut count = 0;
+        let node = complete_tree(10, 4, &mut count);
+        assert_eq!(count, (4usize.pow(10) - 1) / 3);
+        let tree = Path {
+            path: Arc::new(PathPriv {
+                node_vec: vec![node],
+            }),
+        };
+        assert_eq!(tree.count(), count);
+
+        let mut visits = vec![0; count];
+        let mapped = tree.apply_recursive(|d| {
+            visits[*d] += 1;
+            Some(d + count)
+        });
+        assert!(visits.iter().all(|v| *v == 1));
+        let mut v = mapped.to_vec();
+        v.sort();
+        assert_eq!(v, (count..2 * count).collect::<Vec<_>>());
+
+        let mut visits = vec![0; count];
+        let mut acc = Vec::new();
+        let mapped = tree.apply_acc_recursive(&mut acc, |acc, d| {
+            visits[*d] += 1;
+            assert!(!acc.is_empty() && acc.len() <= 10);
+            Some(acc.len())
+        });
+        assert!(acc.is_empty());
+        assert!(visits.iter().all(|v| *v == 1));
+        assert_eq!(
+            mapped.depth_profile(),
+            (0..10).map(|i| 4usize.pow(i)).collect::<Vec<_>>()
+        );
+        assert_eq!(mapped.height(), 10);
+        assert_eq!(*mapped.data(), 1);
+    }
+
+    #[test]
+    fn test_drop_deep_tree() {
+        let mut node = Node::new(0, HashSet::empty());
+        for i in 1..1000000 {
+            node = Node::new(i, HashSet::empty().insert(node));
+        }
+        let tree = Path {
+            path: Arc::new(PathPriv {
+                node_vec: vec![node],
+            }),
+        };
+        let deep = tree.children()[0].children()[0].clone();
+        assert_eq!(*deep.data(), 999997);
+        drop(tree);
+        assert_eq!(deep.depth(), 2);
+        drop(deep);
+    }
+
+    #[test]
+    fn test_root_data() {
+        let root = Path::new(0);
+        assert!(root.is_root());
+        assert_eq!(*root.root_data(), 0);
+
+        let mut p = root.clone();
+        for i in 1..10 {
+            p = p.add_node(i);
+            assert!(!p.is_root());
+            assert_eq!(*p.root_data(), 0);
+            assert_eq!(*p.data(), i);
+        }
+        assert!(p.root().is_root());
+        assert!(p.parent().parent().root_data() == p.root().data());
+
+        let p = p.root().apply(|_| Some(100));
+        assert_eq!(*p.root_data(), 100);
+        assert!(p.children()[0].children()[0].root_data() == &100);
+    }
+
+    #[test]
+    fn test_set_root_data() {
+        let mut p = Path::new(0);
+        for i in 1..10 {
+            p = p.add_node(i).parent().add_node(i * 10);
+        }
+        let count = p.root().count();
+
+        let q = p.set_root_data(100);
+        assert_eq!(*q.root_data(), 100);
+        assert_eq!(*q.root().data(), 100);
+        assert_eq!(*q.data(), 90);
+        assert_eq!(q.depth(), p.depth());
+        assert_eq!(q.ancestors().nth(1), Some(&80));
+        assert_eq!(q.root().count(), count);
+        assert_eq!(*q.parent().parent().data(), 70);
+
+        // the focused node is reachable from the new root
+        let mut r = q.root();
+        for i in 1..10 {
+            r = r.find_child(|d| *d == i * 10).unwrap();
+        }
+        assert!(r == q);
+
+        // the old tree is untouched
+        assert_eq!(*p.root_data(), 0);
+        assert_eq!(*Path::new(1).set_root_data(2).data(), 2);
+    }
+
+    #[test]
+    fn test_apply_at_depth() {
+        let mut p = Path::new(0);
+        for i in 1..6 {
+            p = p.add_node(i).parent().add_node(i * 10);
+        }
+        // 0 -> (1, 10 -> (2, 20 -> (...)))
+        let before = {
+            let mut v = p.root().to_vec();
+            v.sort();
+            v
+        };
+        let check = |q: &Path<i32>, changed: i32, new: i32| {
+            let mut expected = before
+                .iter()
+                .map(|d| if *d == changed { new } else { *d })
+                .collect::<Vec<_>>();
+            expected.sort();
+            let mut v = q.root().to_vec();
+            v.sort();
+            assert_eq!(v, expected);
+            assert_eq!(q.depth(), 5);
+            assert_eq!(q.root().count(), before.len());
+        };
+
+        // the root
+        let q = p.apply_at_depth(0, |d| Some(d + 100));
+        assert!(q.structurally_eq(&p.apply_to_root(|d| Some(d + 100))));
+        assert_eq!(*q.root_data(), 100);
+        assert_eq!(*q.data(), 50);
+        check(&q, 0, 100);
+
+        // a middle ancestor
+        let q = p.apply_at_depth(2, |d| Some(d + 100));
+        assert_eq!(
+            q.ancestors().cloned().collect::<Vec<_>>(),
+            vec![50, 40, 30, 120, 10, 0]
+        );
+        check(&q, 20, 120);
+        let n = q.root().find_descendant(|d| *d == 120).unwrap();
+        assert_eq!(n.depth(), 2);
+        assert_eq!(n.children().len(), 2);
+
+        // the current node
+        let q = p.apply_at_depth(5, |d| Some(d + 100));
+        assert_eq!(*q.data(), 150);
+        check(&q, 50, 150);
+
+        // out of range, or None
+        assert!(p.apply_at_depth(6, |_| Some(0)) == p);
+        assert!(p.apply_at_depth(1, |_| None) == p);
+    }
+
+    #[test]
+    fn test_iter_with_depth() {
+        // single child chains keep the order deterministic
+        let tree = Path::new(0)
+            .add_node(1)
+            .add_node(2)
+            .add_node(3)
+            .parent()
+            .parent()
+            .parent()
+            .add_node(10)
+            .root();
+
+        let mut v = Vec::new();
+        tree.iter_with_depth(&mut |depth, d| v.push((depth, *d)));
+        assert_eq!(v.len(), 5);
+        assert_eq!(v[0], (0, 0));
+        let one = v.iter().position(|e| *e == (1, 1)).unwrap();
+        assert_eq!(v[one + 1..one + 3], [(2, 2), (3, 3)]);
+        assert!(v.contains(&(1, 10)));
+
+        // same order as the recursive walk
+        let mut expected = Vec::new();
+        tree.iter_recursive(&mut |p| expected.push((p.depth(), *p.data())));
+        assert_eq!(v, expected);
+
+        // depths are relative to the current node
+        let mut v = Vec::new();
+        let n1 = tree.find_child(|d| *d == 1).unwrap();
+        n1.iter_with_depth(&mut |depth, d| v.push((depth, *d)));
+        assert_eq!(v, vec![(0, 1), (1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn test_remove_children() {
+        let mut tree = Path::new(0);
+        for i in 1..9 {
+            tree = tree.add_node(i).add_node(i * 10).add_node(i * 100).root();
+        }
+        let n = tree.find_child(|d| *d == 1).unwrap().add_node(11);
+
+        let odd = n.root().remove_children(|d| d % 2 == 0);
+        assert_eq!(odd.children().len(), 4);
+        for c in odd.children() {
+            assert_eq!(c.data() % 2, 1);
+            assert_eq!(c.count(), if *c.data() == 1 { 4 } else { 3 });
+        }
+        assert_eq!(odd.count(), 1 + 4 * 3 + 1);
+
+        // from a deeper node
+        let n1 = n.parent().remove_children(|d| *d == 10);
+        assert_eq!(n1.count(), 2);
+        assert_eq!(n1.root().count(), 1 + 8 * 3 - 1);
+
+        let none = tree.remove_children(|_| true);
+        assert_eq!(none.count(), 1);
+        assert!(none.children().is_empty());
+        assert!(tree.remove_children(|_| false) == tree);
+    }
+
+    #[test]
+    fn test_iter_bfs() {
+        // balanced tree: 3 children per node, 4 levels
+        fn grow(p: Path<i32>, level: usize) -> Path<i32> {
+            let mut p = p;
+            if level < 3 {
+                for i in 0..3 {
+                    let c = grow(p.add_node(*p.data() * 10 + i + 1), level + 1);
+                    p = c.parent();
+                }
+            }
+            p
+        }
+        let tree = grow(Path::new(0), 0);
+        assert_eq!(tree.count(), 1 + 3 + 9 + 27);
+
+        let mut visited = Vec::new();
+        tree.iter_bfs(|p| visited.push((p.depth(), *p.data())));
+        assert_eq!(visited.len(), tree.count());
+        assert_eq!(visited[0], (0, 0));
+        assert!(visited.windows(2).all(|w| w[0].0 <= w[1].0));
+        assert_eq!(
+            visited.iter().filter(|(d, _)| *d == 3).count(),
+            tree.depth_profile()[3]
+        );
+
+        // the children of a node follow `children()`, and the paths keep their ancestry
+        let mut visited = Vec::new();
+        let n1 = tree.find_child(|d| *d == 2).unwrap();
+        n1.iter_bfs(|p| visited.push(p.clone()));
+        let first = n1.children();
+        assert!(visited[1..4].iter().zip(first.iter()).all(|(a, b)| a == b));
+        assert!(visited.iter().all(|p| *p.root().data() == 0));
+        assert!(visited.windows(2).all(|w| w[0].depth() <= w[1].depth()));
+
+        let mut count = 0;
+        Path::new(7).iter_bfs(|_| count += 1);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_clone_as_root() {
+        let mut tree = Path::new(0);
+        for i in 1..4 {
+            tree = tree.add_node(i).add_node(i * 10).add_node(i * 100).root();
+        }
+        let n2 = tree.find_child(|d| *d == 2).unwrap();
+        let n2 = n2.add_node(21).parent();
+
+        let detached = n2.clone_as_root();
+        assert_eq!(detached.len(), 1);
+        assert!(detached.is_root());
+        assert_eq!(*detached.data(), 2);
+        assert_eq!(detached.count(), n2.count());
+        assert!(detached.structurally_eq(&n2));
+        assert!(detached.root() == detached);
+
+        // editing the detached tree doesn't affect the source
+        let edited = detached
+            .apply(|_| Some(5))
+            .add_node(50)
+            .root()
+            .remove_children(|d| *d == 20);
+        assert_eq!(edited.count(), 3);
+        assert_eq!(*n2.data(), 2);
+        assert_eq!(n2.count(), 4);
+        assert_eq!(n2.root().count(), 11);
+
+        let leaf = Path::new(7).clone_as_root();
+        assert_eq!(leaf.count(), 1);
+        assert_eq!(*leaf.data(), 7);
+    }
+
+    #[test]
+    fn test_index_path_navigate() {
+        //        0
+        //      / | \
+        //     1  2  3
+        //    /|     |
+        //   4 5     6
+        //           |
+        //           7
+        let mut tree = Path::new(0);
+        tree = tree.add_node(1).add_node(4).parent().add_node(5).root();
+        tree = tree.add_node(2).root();
+        tree = tree.add_node(3).add_node(6).add_node(7).root();
+
+        let expected = [
+            (1, vec![0]),
+            (2, vec![1]),
+            (3, vec![2]),
+            (4, vec![0, 0]),
+            (5, vec![0, 1]),
+            (6, vec![2, 0]),
+            (7, vec![2, 0, 0]),
+        ];
+        for (d, indices) in expected.iter() {
+            let p = tree.find_descendant(|x| x == d).unwrap();
+            assert_eq!(&p.index_path(), indices);
+            let back = Path::navigate(&tree, indices).unwrap();
+            assert!(back == p);
+            assert_eq!(back.data(), d);
+        }
+        assert!(tree.index_path().is_empty());
+        assert!(Path::navigate(&tree, &[]).unwrap() == tree);
+
+        // the index path survives edits elsewhere in the tree
+        let p7 = tree.find_descendant(|x| *x == 7).unwrap();
+        let edited = tree.add_node(8).root();
+        let back = Path::navigate(&edited, &p7.index_path()).unwrap();
+        assert_eq!(*back.data(), 7);
+        assert_eq!(back.index_path(), p7.index_path());
+
+        // out of bounds
+        assert!(Path::navigate(&tree, &[3]).is_none());
+        assert!(Path::navigate(&tree, &[1, 0]).is_none());
+        assert!(Path::navigate(&tree, &[2, 0, 0, 0]).is_none());
+    }
+
+    #[test]
+    fn test_truncate_depth() {
+        // 2 children per node, 5 levels below the root
+        fn grow(p: Path<i32>, level: usize) -> Path<i32> {
+            let mut p = p;
+            if level < 5 {
+                for i in 0..2 {
+                    p = grow(p.add_node(*p.data() * 10 + i + 1), level + 1).parent();
+                }
+            }
+            p
+        }
+        let tree = grow(Path::new(0), 0);
+        assert_eq!(tree.count(), 63);
+        assert_eq!(tree.height(), 6);
+
+        let t2 = tree.truncate_depth(2);
+        assert_eq!(t2.count(), 1 + 2 + 4);
+        assert_eq!(t2.depth_profile(), vec![1, 2, 4]);
+        assert!(t2.is_root());
+        // the source is untouched
+        assert_eq!(tree.count(), 63);
+
+        // from a deeper node: the rest of the tree is kept
+        let n1 = tree.find_child(|d| *d == 1).unwrap();
+        let t = n1.truncate_depth(1);
+        assert_eq!(*t.data(), 1);
+        assert_eq!(t.depth(), 1);
+        assert_eq!(t.count(), 3);
+        assert_eq!(t.root().count(), 1 + 3 + 31);
+
+        let t0 = tree.truncate_depth(0);
+        assert_eq!(t0.count(), 1);
+        assert!(t0.children().is_empty());
+        assert!(tree.truncate_depth(5) == tree);
+        assert!(tree.truncate_depth(100) == tree);
+    }
+
+    #[test]
+    fn test_is_leaf_child_count() {
+        //      0
+        //    / | \
+        //   1  2  3
+        //   |
+        //   4
+        let mut tree = Path::new(0);
+        assert!(tree.is_leaf());
+        assert_eq!(tree.child_count(), 0);
+        tree = tree.add_node(1).add_node(4).root();
+        tree = tree.add_node(2).root().add_node(3).root();
+
+        assert!(!tree.is_leaf());
+        assert_eq!(tree.child_count(), 3);
+        assert_eq!(tree.child_count(), tree.children().len());
+
+        let n1 = tree.find_child(|d| *d == 1).unwrap();
+        assert!(!n1.is_leaf());
+        assert_eq!(n1.child_count(), 1);
+
+        for d in [2, 3] {
+            let n = tree.find_child(|x| *x == d).unwrap();
+            assert!(n.is_leaf());
+            assert_eq!(n.child_count(), 0);
+        }
+        let n4 = n1.children()[0].clone();
+        assert!(n4.is_leaf());
+        assert!(n4.remove_node().is_leaf());
+        assert_eq!(tree.remove_all_children().child_count(), 0);
+    }
 }